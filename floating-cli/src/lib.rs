@@ -1,44 +1,175 @@
 use floating::*;
-use half::f16;
-use num_bigint::BigUint;
+use half::{bf16, f16};
+use num_bigint::{BigInt, BigUint};
 use std::cmp::min;
 
-fn float_to_hex_inner<T: FloatType, W: std::io::Write>(w: &mut W, num: T) -> anyhow::Result<()> {
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    // FloPoCo (wE, wF), defaults to the widths of each IEEE format
+    pub flopoco: Option<(usize, usize)>,
+    // print intermediate values of a+b and a-b
+    pub trace: bool,
+    // print the neighbors and the ulp of each value
+    pub neighbors: bool,
+    // target behavior of a+b and a-b
+    pub env: FpEnv,
+}
+
+impl Options {
+    // parse an option like --flopoco=10,30
+    // returns false if arg is not an option
+    pub fn parse(&mut self, arg: &str) -> anyhow::Result<bool> {
+        if let Some(widths) = arg.strip_prefix("--flopoco=") {
+            let (we, wf) = widths
+                .split_once(',')
+                .ok_or_else(|| anyhow::anyhow!("expected --flopoco=wE,wF"))?;
+            let (we, wf) = (we.parse::<usize>()?, wf.parse::<usize>()?);
+            if !(2..=FLOPOCO_MAX_WE).contains(&we) || wf < 1 {
+                anyhow::bail!("unsupported FloPoCo format wE={},wF={}", we, wf);
+            }
+            self.flopoco = Some((we, wf));
+        } else if arg == "--trace" {
+            self.trace = true;
+        } else if arg == "--neighbors" {
+            self.neighbors = true;
+        } else if let Some(name) = arg.strip_prefix("--profile=") {
+            let profile = Profile::from_name(name).ok_or_else(|| {
+                let names: Vec<&str> = Profile::ALL.iter().map(|p| p.name()).collect();
+                anyhow::anyhow!(
+                    "unknown profile {}, expected one of {}",
+                    name,
+                    names.join(", ")
+                )
+            })?;
+            self.env = FpEnv::profile(profile);
+        } else if arg.starts_with("--") {
+            anyhow::bail!("unknown option {}", arg);
+        } else {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+}
+
+fn float_to_hex_inner<T: FloatType, W: std::io::Write>(
+    w: &mut W,
+    num: T,
+    exact: Option<&(BigInt, BigUint)>,
+    options: &Options,
+) -> anyhow::Result<()> {
     let bits = num.to_biguint();
     let hardfloat = to_hardfloat::<T>(&bits);
-    let flopoco = to_flopoco::<T>(&bits);
+    let (we, wf) = options.flopoco.unwrap_or((T::EXP, T::SIG - 1));
+    let flopoco = to_flopoco_custom::<T>(&bits, we, wf);
     writeln!(
         w,
-        "    {}: {:#x}({})",
-        T::NAME,
+        "    {}: {:#x}({}) {} shortest={} exact={}",
+        T::name(),
         bits,
-        print_float::<T>(&bits)
+        print_float::<T>(&bits),
+        print_hex_float::<T>(&bits),
+        print_shortest_decimal::<T>(&bits),
+        print_exact_decimal::<T>(&bits)
+    )?;
+    let class = softfloat_float_class(num);
+    writeln!(
+        w,
+        "    class{}: {} fclass={:#05x} vfpclass={:#04x}",
+        T::name(),
+        class,
+        class.to_fclass(),
+        class.to_vfpclass()
     )?;
+    if let Some((num, den)) = exact {
+        // error of rounding the input to this format
+        match conversion_error::<T>(&bits, num, den) {
+            Some(error) if error.exact => writeln!(w, "    err{}: exact", T::name())?,
+            Some(error) => writeln!(
+                w,
+                "    err{}: abs={}e{} rel={:e} ulp={:e}",
+                T::name(),
+                error.abs_error.0,
+                error.abs_error.1,
+                error.rel_error,
+                error.ulp_error
+            )?,
+            None => writeln!(w, "    err{}: overflow", T::name())?,
+        }
+    }
+    if options.neighbors {
+        let show = |num: T| {
+            let bits = num.to_biguint();
+            format!("{}({:#x})", print_shortest_decimal::<T>(&bits), bits)
+        };
+        writeln!(
+            w,
+            "    nb{}: next_down={} next_up={} ulp={}",
+            T::name(),
+            show(next_down(num)),
+            show(next_up(num)),
+            show(ulp(num))
+        )?;
+    }
     writeln!(
         w,
         "    h{}: {:#x}({})",
-        T::NAME,
+        T::name(),
         hardfloat,
         print_hardfloat::<T>(&hardfloat)
     )?;
+    writeln!(
+        w,
+        "    raw{}: {}",
+        T::name(),
+        print_raw_float(&to_raw_float::<T>(&bits))
+    )?;
+    let flopoco_name = match options.flopoco {
+        Some((we, wf)) => format!("{}[wE={},wF={}]", T::name(), we, wf),
+        None => T::name(),
+    };
     writeln!(
         w,
         "    fpc{}: {:#x}({})",
-        T::NAME,
+        flopoco_name,
         flopoco,
-        print_flopoco::<T>(&flopoco)
+        print_flopoco_custom(&flopoco, we, wf)
     )?;
     Ok(())
 }
 
-fn float_to_hex<W: std::io::Write>(w: &mut W, num: f64) -> anyhow::Result<()> {
+fn float_to_hex<W: std::io::Write>(w: &mut W, num: &str, options: &Options) -> anyhow::Result<()> {
     writeln!(w, "  float -> hex:")?;
-    float_to_hex_inner::<f16, W>(w, f16::from_f64(num))?;
-    float_to_hex_inner::<f32, W>(w, num as f32)?;
-    float_to_hex_inner::<f64, W>(w, num)?;
+    let exact = if is_hex_float(num) {
+        hex_float_to_rational(num)
+    } else {
+        decimal_to_rational(num)
+    };
+    let exact = exact.as_ref();
+    float_to_hex_inner::<f16, W>(w, parse(num)?, exact, options)?;
+    float_to_hex_inner::<f32, W>(w, parse(num)?, exact, options)?;
+    float_to_hex_inner::<f64, W>(w, parse(num)?, exact, options)?;
     Ok(())
 }
 
+// hex float literals like 0x1.8p+3, unlike raw bits, have a point or an exponent
+fn is_hex_float(arg: &str) -> bool {
+    let arg = arg.trim_start_matches(['+', '-']);
+    (arg.starts_with("0x") || arg.starts_with("0X")) && arg.contains(['.', 'p', 'P'])
+}
+
+// round the decimal or hex float string directly to each format
+// in the default environment, so that a profile with FTZ still shows subnormal inputs
+fn parse<T: FloatType>(num: &str) -> anyhow::Result<T> {
+    let env = FpEnv::default();
+    if is_hex_float(num) {
+        parse_hex_float::<T>(num, &env)
+    } else {
+        parse_decimal::<T>(num, &env)
+    }
+    .map(|(res, _)| res)
+    .ok_or_else(|| anyhow::anyhow!("invalid number {}", num))
+}
+
 fn hex_to_float_inner<T: FloatType, W: std::io::Write>(
     w: &mut W,
     num: &BigUint,
@@ -55,9 +186,9 @@ fn hex_to_float_inner<T: FloatType, W: std::io::Write>(
         offset += T::WIDTH;
     }
 
-    write!(w, "    {}:", T::NAME)?;
+    write!(w, "    {}:", T::name())?;
     for num in numbers.iter().rev() {
-        write!(w, " {}", num)?;
+        write!(w, " {}({})", num, softfloat_float_class(*num))?;
     }
     writeln!(w)?;
     Ok(())
@@ -72,21 +203,245 @@ fn hex_to_float<T: std::io::Write>(w: &mut T, num: &BigUint) -> anyhow::Result<(
     Ok(())
 }
 
-pub fn process_arg<T: std::io::Write>(w: &mut T, arg: &str) -> anyhow::Result<()> {
+fn add_sub_inner<T: FloatType, W: std::io::Write>(
+    w: &mut W,
+    a: T,
+    op: char,
+    b: T,
+    options: &Options,
+) -> anyhow::Result<()> {
+    let (res, trace) = if op == '+' {
+        softfloat_add_trace(a, b, &options.env)
+    } else {
+        softfloat_sub_trace(a, b, &options.env)
+    };
+    writeln!(
+        w,
+        "    {}: {} {} {} = {} ({:#x})",
+        T::name(),
+        a,
+        op,
+        b,
+        res,
+        res.to_biguint()
+    )?;
+    if options.trace {
+        for line in print_add_trace::<T>(&trace).lines() {
+            writeln!(w, "      {}", line)?;
+        }
+    }
+    Ok(())
+}
+
+fn add_sub<W: std::io::Write>(
+    w: &mut W,
+    a: &str,
+    op: char,
+    b: &str,
+    options: &Options,
+) -> anyhow::Result<()> {
+    writeln!(w, "  softfloat:")?;
+    add_sub_inner::<f16, W>(w, parse(a)?, op, parse(b)?, options)?;
+    add_sub_inner::<f32, W>(w, parse(a)?, op, parse(b)?, options)?;
+    add_sub_inner::<f64, W>(w, parse(a)?, op, parse(b)?, options)?;
+    Ok(())
+}
+
+// split "a+b" or "a-b", skipping the sign of a and of exponents
+// in both decimal and hex float literals
+fn split_add_sub(arg: &str) -> Option<(&str, char, &str)> {
+    let bytes = arg.as_bytes();
+    (1..bytes.len())
+        .find(|&i| {
+            (bytes[i] == b'+' || bytes[i] == b'-')
+                && !matches!(bytes[i - 1], b'e' | b'E' | b'p' | b'P' | b'+' | b'-')
+        })
+        .map(|i| (&arg[..i], bytes[i] as char, &arg[i + 1..]))
+}
+
+fn write_format_value<W: std::io::Write>(
+    w: &mut W,
+    name: &str,
+    value: &FormatValue,
+) -> anyhow::Result<()> {
+    let one = BigUint::from(1u32);
+    let power = if value.sig == one {
+        format!("2^{}", value.exp)
+    } else {
+        format!("{}*2^{}", value.sig, value.exp)
+    };
+    writeln!(
+        w,
+        "  {}: {:#x} = {} = {}",
+        name,
+        value.bits,
+        power,
+        print_dyadic(&value.sig, value.exp)
+    )?;
+    Ok(())
+}
+
+// print the characteristics of a named format like f32,
+// or of a custom format given as EXP,SIG where SIG includes the implicit bit
+pub fn print_format_info<W: std::io::Write>(w: &mut W, format: &str) -> anyhow::Result<()> {
+    let info = match format {
+        "f16" => format_info::<f16>(),
+        "bf16" => format_info::<bf16>(),
+        "f32" => format_info::<f32>(),
+        "f64" => format_info::<f64>(),
+        "f128" => format_info::<CustomFloat<15, 113>>(),
+        _ => {
+            let (exp, sig) = format
+                .split_once(',')
+                .ok_or_else(|| anyhow::anyhow!("unknown format {}, expected EXP,SIG", format))?;
+            let (exp, sig) = (exp.parse::<usize>()?, sig.parse::<usize>()?);
+            if !(2..=CUSTOM_MAX_EXP).contains(&exp) || sig < 1 {
+                anyhow::bail!("unsupported format EXP={},SIG={}", exp, sig);
+            }
+            format_info_custom(exp, sig)
+        }
+    };
+    writeln!(
+        w,
+        "{}: EXP={} SIG={} width={}",
+        format, info.exp_bits, info.sig_bits, info.width
+    )?;
+    writeln!(w, "  bias: {}", info.bias)?;
+    writeln!(w, "  emin: {}", info.emin)?;
+    writeln!(w, "  emax: {}", info.emax)?;
+    writeln!(w, "  digits: {}", info.digits)?;
+    writeln!(w, "  max digits: {}", info.max_digits)?;
+    write_format_value(w, "epsilon", &info.epsilon)?;
+    match &info.min_subnormal {
+        Some(value) => write_format_value(w, "min subnormal", value)?,
+        None => writeln!(w, "  min subnormal: none")?,
+    }
+    write_format_value(w, "min normal", &info.min_normal)?;
+    write_format_value(w, "max finite", &info.max_finite)?;
+    write_format_value(w, "max consecutive integer", &info.max_consecutive_integer)?;
+    Ok(())
+}
+
+// a decimal or hex float literal, or raw bits like 0x3f800000
+fn parse_or_bits<T: FloatType>(num: &str) -> anyhow::Result<T> {
+    match num.strip_prefix("0x") {
+        Some(bits) if !is_hex_float(num) => BigUint::parse_bytes(bits.as_bytes(), 16)
+            .filter(|bits| bits.bits() as usize <= T::WIDTH)
+            .map(|bits| T::from_biguint(&bits))
+            .ok_or_else(|| anyhow::anyhow!("invalid {} bits {}", T::name(), num)),
+        _ => parse(num),
+    }
+}
+
+fn correctly_rounded_inner<T: FloatType, W: std::io::Write>(
+    w: &mut W,
+    format: &str,
+    func: &str,
+    args: &[String],
+) -> anyhow::Result<()> {
+    let env = FpEnv::default();
+    let arity = if func == "pow" { 2 } else { 1 };
+    if args.len() < arity || args.len() > arity + 1 {
+        anyhow::bail!("expected {} argument(s) and an optional candidate", arity);
+    }
+    let x: T = parse_or_bits(&args[0])?;
+    let (res, flags) = match func {
+        "exp" => cr_exp(x, &env),
+        "log" => cr_log(x, &env),
+        "log2" => cr_log2(x, &env),
+        "sin" => cr_sin(x, &env),
+        "cos" => cr_cos(x, &env),
+        "tan" => cr_tan(x, &env),
+        "atan" => cr_atan(x, &env),
+        "pow" => cr_pow(x, parse_or_bits(&args[1])?, &env),
+        _ => anyhow::bail!(
+            "unknown function {}, expected one of exp, log, log2, sin, cos, tan, atan, pow",
+            func
+        ),
+    };
+    let names = [
+        (flags.invalid, "invalid"),
+        (flags.div_by_zero, "div_by_zero"),
+        (flags.overflow, "overflow"),
+        (flags.underflow, "underflow"),
+        (flags.inexact, "inexact"),
+    ];
+    let names: Vec<&str> = names
+        .iter()
+        .filter_map(|&(set, name)| set.then_some(name))
+        .collect();
+    writeln!(w, "{}({}) in {}:", func, args[..arity].join(", "), format)?;
+    writeln!(
+        w,
+        "  correctly rounded: {} ({:#x}) [{}]",
+        res,
+        res.to_biguint(),
+        names.join(" ")
+    )?;
+    if let Some(candidate) = args.get(arity) {
+        let candidate: T = parse_or_bits(candidate)?;
+        let verdict = match ulp_distance(res, candidate) {
+            Some(distance) if distance == BigUint::default() => "correctly rounded".to_string(),
+            Some(distance) => format!("{} ulp away", distance),
+            // nan payloads are not compared
+            None if decode_nan(res).is_some() && decode_nan(candidate).is_some() => {
+                "correctly rounded".to_string()
+            }
+            None => "not correctly rounded".to_string(),
+        };
+        writeln!(
+            w,
+            "  candidate: {} ({:#x}) {}",
+            candidate,
+            candidate.to_biguint(),
+            verdict
+        )?;
+    }
+    Ok(())
+}
+
+// print the correctly rounded f(x) or pow(x, y) in a named format,
+// and how far a candidate result is from it
+pub fn print_correctly_rounded<W: std::io::Write>(
+    w: &mut W,
+    args: &[String],
+) -> anyhow::Result<()> {
+    let [format, func, args @ ..] = args else {
+        anyhow::bail!("expected FORMAT FUNCTION ARGS [CANDIDATE]");
+    };
+    match format.as_str() {
+        "f16" => correctly_rounded_inner::<f16, W>(w, format, func, args),
+        "bf16" => correctly_rounded_inner::<bf16, W>(w, format, func, args),
+        "f32" => correctly_rounded_inner::<f32, W>(w, format, func, args),
+        "f64" => correctly_rounded_inner::<f64, W>(w, format, func, args),
+        "f128" => correctly_rounded_inner::<CustomFloat<15, 113>, W>(w, format, func, args),
+        _ => anyhow::bail!(
+            "unknown format {}, expected f16, bf16, f32, f64 or f128",
+            format
+        ),
+    }
+}
+
+pub fn process_arg<T: std::io::Write>(
+    w: &mut T,
+    arg: &str,
+    options: &Options,
+) -> anyhow::Result<()> {
     writeln!(w, "{}:", arg)?;
-    if arg.starts_with("0x") {
+    if arg.starts_with("0x") && !is_hex_float(arg) {
         let s = arg.trim_start_matches("0x");
         if let Some(num) = BigUint::parse_bytes(s.as_bytes(), 16) {
             hex_to_float(w, &num)?;
         }
-    } else if let Ok(num) = arg.parse::<u64>() {
+    } else if arg.parse::<u64>().is_ok() {
         if let Some(num) = BigUint::parse_bytes(arg.as_bytes(), 10) {
             hex_to_float(w, &num)?;
         }
-        float_to_hex(w, num as f64)?;
+        float_to_hex(w, arg, options)?;
+    } else if let Some((a, op, b)) = split_add_sub(arg) {
+        add_sub(w, a, op, b, options)?;
     } else {
-        let num = arg.parse::<f64>()?;
-        float_to_hex(w, num)?;
+        float_to_hex(w, arg, options)?;
     };
     Ok(())
 }
@@ -1,9 +1,26 @@
-use floating_cli::process_arg;
+use floating_cli::{print_correctly_rounded, print_format_info, process_arg, Options};
 use std::env::args;
 
 fn main() -> anyhow::Result<()> {
+    // info f32 bf16 5,11: print format characteristics
+    if args().nth(1).as_deref() == Some("info") {
+        for format in args().skip(2) {
+            print_format_info(&mut std::io::stdout(), &format)?;
+        }
+        return Ok(());
+    }
+    // cr f64 exp 1 2.718281828459045: print the correctly rounded exp(1) next to a candidate
+    if args().nth(1).as_deref() == Some("cr") {
+        let args: Vec<String> = args().skip(2).collect();
+        return print_correctly_rounded(&mut std::io::stdout(), &args);
+    }
+
+    // options apply to the numbers after them
+    let mut options = Options::default();
     for arg in args().skip(1) {
-        process_arg(&mut std::io::stdout(), &arg)?;
+        if !options.parse(&arg)? {
+            process_arg(&mut std::io::stdout(), &arg, &options)?;
+        }
     }
     Ok(())
 }
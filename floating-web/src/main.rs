@@ -1,6 +1,6 @@
-use std::io::Cursor;
+use std::io::{Cursor, Write};
 
-use floating_cli::process_arg;
+use floating_cli::{process_arg, Options};
 use wasm_bindgen::{JsCast, UnwrapThrowExt};
 use web_sys::{Event, HtmlInputElement};
 use yew::prelude::*;
@@ -13,31 +13,57 @@ fn get_value_from_input_event(e: Event) -> String {
     target.value()
 }
 
+fn process_input(input: &str, trace: bool) -> String {
+    let mut buffer = Vec::new();
+    let mut cursor = Cursor::new(&mut buffer);
+    let mut options = Options {
+        trace,
+        ..Options::default()
+    };
+    for part in input.split(" ").filter(|part| !part.is_empty()) {
+        let res = match options.parse(part) {
+            Ok(true) => Ok(()),
+            Ok(false) => process_arg(&mut cursor, part, &options),
+            Err(err) => Err(err),
+        };
+        if let Err(err) = res {
+            writeln!(cursor, "{}: {}", part, err).unwrap();
+        }
+    }
+    String::from_utf8_lossy(&buffer).into_owned()
+}
+
 #[function_component]
 fn App() -> Html {
     let input = use_state(String::new);
     let input_value = (*input).clone();
-    let result = use_state(String::new);
-    let result_value = (*result).clone();
+    let trace = use_state(|| false);
+    let trace_value = *trace;
+    let result_value = process_input(&input_value, trace_value);
 
     let oninput = Callback::from(move |input_event: Event| {
         let new_input = get_value_from_input_event(input_event);
-        let mut buffer = Vec::new();
-        let mut cursor = Cursor::new(&mut buffer);
-        for part in new_input.split(" ") {
-            process_arg(&mut cursor, part).unwrap();
-        }
         input.set(new_input);
-        result.set(String::from_utf8_lossy(&buffer).into_owned());
+    });
+
+    let ontrace = Callback::from(move |event: Event| {
+        let event_target = event.target().unwrap_throw();
+        let target: HtmlInputElement = event_target.dyn_into().unwrap_throw();
+        trace.set(target.checked());
     });
 
     html! {
         <div>
             <h1>{"Input"}</h1>
-            {"Input decimal or hexadecimal numbers separated by spaces and press Enter:"}
+            {"Input decimal or hexadecimal numbers, or a+b and a-b, separated by spaces and press Enter:"}
             <br/>
             <input type="text" value={input_value} onchange={oninput} />
             <br/>
+            <label>
+                <input type="checkbox" checked={trace_value} onchange={ontrace} />
+                {"Trace a+b and a-b step by step"}
+            </label>
+            <br/>
             <h1>{"Result"}</h1>
             <br/>
             <pre>
@@ -1,40 +1,199 @@
-use crate::{extract, pack, FloatType};
+use crate::{
+    extract, flush_input, flush_output, invalid_nan, pack, print_float, propagate_nan,
+    stochastic_up, BigFloat, ExceptionFlags, FloatType, FpEnv, RoundingMode,
+};
 use num_bigint::{BigUint, ToBigUint};
 
-// round to nearest even with 3 bits: guard, round and sticky
-fn round(man: &BigUint) -> BigUint {
+// round with 3 bits: guard, round and sticky
+// stochastic rounding needs every discarded bit instead,
+// exact returns them and their count
+fn round(
+    man: &BigUint,
+    sign: bool,
+    rm: RoundingMode,
+    exact: impl FnOnce() -> (BigUint, u64),
+) -> BigUint {
     let one = 1.to_biguint().unwrap();
 
     let low_bits = man.to_u64_digits().pop().unwrap_or(0) & 0b111;
     let mut res: BigUint = man >> 3;
-    if low_bits < 0b100 {
-        // round down
-    } else if low_bits > 0b100 {
-        // round up
-        res += &one;
-    } else {
+    let up = match rm {
         // round to nearest even
-        if res.bit(0) {
-            // up
-            res += &one;
+        RoundingMode::NearestEven => low_bits > 0b100 || (low_bits == 0b100 && res.bit(0)),
+        RoundingMode::NearestMaxMag => low_bits >= 0b100,
+        RoundingMode::TowardZero => false,
+        RoundingMode::TowardNegative => low_bits != 0 && sign,
+        RoundingMode::TowardPositive => low_bits != 0 && !sign,
+        RoundingMode::Stochastic(random) if low_bits != 0 => {
+            let (rem, shift) = exact();
+            stochastic_up(&rem, shift, random)
         }
+        RoundingMode::Stochastic(_) => false,
+    };
+    if up {
+        res += &one;
     }
     res
 }
 
+// bits of the exact sum below the lsb of a result with biased exponent exp
+fn exact_remainder(exp: u64, trace: &AddTrace) -> (BigUint, u64) {
+    let one = 1.to_biguint().unwrap();
+    // magnitudes of the unaligned operands, negating b for effective_sub,
+    // scaled so that the lowest datapath bit at biased exponent exp weighs 2^exp
+    let a = BigFloat::new(false, trace.sig_a.clone(), trace.exp_a as i64);
+    let b = BigFloat::new(trace.effective_sub, trace.sig_b.clone(), trace.exp_b as i64);
+    // the precision is never reached, the sum is exact
+    let (sum, _) = a.add(&b, usize::MAX >> 1, RoundingMode::TowardZero);
+    // the result keeps 3 bits less than the datapath
+    let shift = (exp as i64 + 3 - sum.exp()).max(0) as u64;
+    (sum.sig() & ((one << shift) - 1u32), shift)
+}
+
+// reduced OR of the lowest shift bits
+fn sticky_bit(man: &BigUint, shift: u64) -> bool {
+    man.trailing_zeros().is_some_and(|zeros| zeros < shift)
+}
+
 // right shift with the LSB sticky
 // sticky bit: reduced OR of shifted-away bits
 fn rshift_sticky(man: &BigUint, shift: u64) -> BigUint {
-    let zero = 0.to_biguint().unwrap();
     let one = 1.to_biguint().unwrap();
 
-    if (man & ((&one << shift) - &one)) != zero {
+    if sticky_bit(man, shift) {
         (man >> shift) | one
     } else {
         man >> shift
     }
 }
 
+// biased exponent, at least 1 for subnormal numbers,
+// and significand with the implicit 1.0 and 3 bits for rounding
+fn unpack<T: FloatType>(exp: &BigUint, man: &BigUint) -> (u64, BigUint) {
+    let zero = 0.to_biguint().unwrap();
+    let one = 1.to_biguint().unwrap();
+    let norm_bit = &one << (T::SIG - 1);
+    if exp == &zero {
+        // subnormal/zero
+        (1, man << 3)
+    } else {
+        // add implicit 1.0
+        (
+            exp.to_u64_digits().pop().unwrap_or(0),
+            (man + norm_bit) << 3,
+        )
+    }
+}
+
+// intermediate values of softfloat_add/softfloat_sub
+// significands keep the implicit 1.0 and 3 bits for rounding
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AddTrace {
+    // signs differ after considering the operation
+    pub effective_sub: bool,
+    // inf/nan operand, the remaining fields are not used
+    pub special: bool,
+    // biased exponents, at least 1 for subnormal numbers
+    pub exp_a: u64,
+    pub exp_b: u64,
+    pub exp_diff: u64,
+    pub sig_a: BigUint,
+    pub sig_b: BigUint,
+    // significands after aligning the smaller one
+    pub aligned_a: BigUint,
+    pub aligned_b: BigUint,
+    // reduced OR of the bits shifted away by alignment
+    pub sticky: bool,
+    // sum or difference of the aligned significands
+    pub sum: BigUint,
+    // left shift to normalize, -1 for carry out
+    pub norm_shift: i64,
+    // normalized significand before rounding
+    pub norm: BigUint,
+    pub guard: bool,
+    pub round: bool,
+    pub sticky_round: bool,
+    pub round_up: bool,
+    // biased exponent after normalization and rounding
+    pub exp_c: u64,
+    pub result: BigUint,
+    pub flags: ExceptionFlags,
+    // rounding mode of the operation
+    pub rounding: RoundingMode,
+}
+
+// normalize the pre shifted significand, round and pack
+fn normalize<T: FloatType>(sign: BigUint, exp: u64, man: BigUint, trace: &mut AddTrace) -> T {
+    let zero = 0.to_biguint().unwrap();
+    let one = 1.to_biguint().unwrap();
+    let norm_bit = &one << (T::SIG - 1);
+
+    let mut exp = exp;
+    let mut man = man;
+    trace.sum = man.clone();
+    if man >= &norm_bit << 4 {
+        // carry out of addition
+        man = rshift_sticky(&man, 1);
+        exp += 1;
+        trace.norm_shift = -1;
+    } else if man != zero {
+        // cancellation of subtraction
+        // left shift until normalized, but not below the subnormal exponent
+        let shift = (T::SIG as u64 + 3 - man.bits()).min(exp - 1);
+        man <<= shift;
+        exp -= shift;
+        trace.norm_shift = shift as i64;
+    }
+    trace.norm = man.clone();
+    trace.guard = man.bit(2);
+    trace.round = man.bit(1);
+    trace.sticky_round = man.bit(0);
+
+    // rounding and remove pre shifted bits
+    man = round(&man, sign.bits() != 0, trace.rounding, || {
+        exact_remainder(exp, trace)
+    });
+    trace.round_up = man != &trace.norm >> 3;
+    // tiny sums are exact, so underflow is only raised by FTZ
+    trace.flags.inexact = trace.guard || trace.round || trace.sticky_round;
+    if man >= &norm_bit << 1 {
+        // carry out of rounding
+        man >>= 1;
+        exp += 1;
+    }
+
+    let exp = if man < norm_bit {
+        // subnormal/zero
+        zero.clone()
+    } else {
+        man -= &norm_bit;
+        exp.to_biguint().unwrap()
+    };
+    let (exp, man) = if exp >= T::max_exp() {
+        // overflow to inf, or to the largest finite number when rounding toward zero
+        trace.flags.overflow = true;
+        trace.flags.inexact = true;
+        let to_inf = match trace.rounding {
+            RoundingMode::NearestEven
+            | RoundingMode::NearestMaxMag
+            | RoundingMode::Stochastic(_) => true,
+            RoundingMode::TowardZero => false,
+            RoundingMode::TowardNegative => sign.bits() != 0,
+            RoundingMode::TowardPositive => sign.bits() == 0,
+        };
+        if to_inf {
+            (T::max_exp(), zero)
+        } else {
+            (T::max_exp() - 1u32, norm_bit - 1u32)
+        }
+    } else {
+        (exp, man)
+    };
+    trace.exp_c = exp.to_u64_digits().pop().unwrap_or(0);
+    trace.result = pack::<T>(&sign, &exp, &man);
+    T::from_biguint(&trace.result)
+}
+
 fn effective_add<T: FloatType>(
     sign_a: BigUint,
     exp_a: BigUint,
@@ -42,113 +201,43 @@ fn effective_add<T: FloatType>(
     sign_b: BigUint,
     exp_b: BigUint,
     man_b: BigUint,
+    trace: &mut AddTrace,
 ) -> T {
-    let zero = 0.to_biguint().unwrap();
-    let one = 1.to_biguint().unwrap();
-    let two = 2.to_biguint().unwrap();
-    let three = 3.to_biguint().unwrap();
-    let norm_bit = &one << (T::SIG - 1);
-
-    let (sign_c, exp_c, man_c) = if exp_a == exp_b {
-        // case 1: exponent equals
-        if exp_a == zero {
-            // case 1.1: subnormal/zero + subnormal/zero
-            // sum up mantissa
-            let sign_c = sign_a;
-            let exp_c = zero;
-            let man_c = &man_a + &man_b;
-            (sign_c, exp_c, man_c)
-        } else if exp_a == T::max_exp() {
-            // case 1.2: inf/nan + inf/nan
-            // propagate nan
-            if man_a != zero {
-                // nan
-                (sign_a, T::max_exp(), man_a)
-            } else if man_b != zero {
-                // nan
-                (sign_b, T::max_exp(), man_b)
-            } else {
-                // inf
-                (sign_a, exp_a, man_a)
-            }
-        } else {
-            // case 1.3: normal + normal
-            // add implicit 1.0
-            let norm_a = man_a + &norm_bit;
-            let norm_b = man_b + &norm_bit;
-
-            let sign_c = sign_a;
-            let exp_c = exp_a + &one;
-            let mut man_c = norm_a + norm_b;
-
-            // normalize and rounding to nearest even
-            // if the lowest two bits are 0b11
-            // it should be rounded up
-            if (&man_c & &three) == three {
-                man_c += two;
-            }
-            man_c >>= 1;
-            man_c -= norm_bit;
-            (sign_c, exp_c, man_c)
-        }
-    } else {
-        // case: exponent differs
-        if exp_a == T::max_exp() {
-            // inf/nan
+    if exp_a == T::max_exp() || exp_b == T::max_exp() {
+        // inf + x, nan is handled by the caller
+        let (sign_c, exp_c, man_c) = if exp_a == T::max_exp() {
+            // inf
             (sign_a, exp_a, man_a)
-        } else if exp_b == T::max_exp() {
-            // inf/nan
-            (sign_b, exp_b, man_b)
         } else {
-            let mut norm_a = man_a;
-            let mut norm_b = man_b;
-
-            // pre left shift 3 bits for rounding
-            norm_a <<= 3;
-            norm_b <<= 3;
-
-            let mut exp_c = if exp_a > exp_b {
-                // exp_a > exp_b
-                let exp_diff = (&exp_a - &exp_b).to_u64_digits().pop().unwrap_or(0);
-                if exp_b != zero {
-                    // add implicit 1.0
-                    norm_b += &norm_bit << 3;
-                }
-
-                // align with sticky bit
-                norm_b = rshift_sticky(&norm_b, exp_diff);
-                exp_a
-            } else {
-                // exp_a < exp_b
-                let exp_diff = (&exp_b - &exp_a).to_u64_digits().pop().unwrap_or(0);
-                if exp_a != zero {
-                    // add implicit 1.0
-                    norm_a += &norm_bit << 3;
-                }
-
-                // align with sticky bit
-                norm_a = rshift_sticky(&norm_a, exp_diff);
-                exp_b
-            };
-
-            // the bigger one is always normal
-            let mut man_c = norm_a + norm_b + (&norm_bit << 3);
-
-            if man_c >= &norm_bit << 4 {
-                exp_c += &one;
-                man_c >>= 1;
-            }
-
-            // rounding and remove pre shifted bits
-            man_c = round(&man_c);
-
-            man_c -= &norm_bit;
+            // inf
+            (sign_b, exp_b, man_b)
+        };
+        trace.special = true;
+        trace.result = pack::<T>(&sign_c, &exp_c, &man_c);
+        return T::from_biguint(&trace.result);
+    }
 
-            let sign_c = sign_a;
-            (sign_c, exp_c, man_c)
-        }
+    let (exp_a, norm_a) = unpack::<T>(&exp_a, &man_a);
+    let (exp_b, norm_b) = unpack::<T>(&exp_b, &man_b);
+    trace.exp_a = exp_a;
+    trace.exp_b = exp_b;
+    trace.exp_diff = exp_a.abs_diff(exp_b);
+    trace.sig_a = norm_a.clone();
+    trace.sig_b = norm_b.clone();
+
+    // align the smaller one with sticky bit
+    let (exp_c, norm_a, norm_b) = if exp_a >= exp_b {
+        (exp_a, norm_a, rshift_sticky(&norm_b, exp_a - exp_b))
+    } else {
+        (exp_b, rshift_sticky(&norm_a, exp_b - exp_a), norm_b)
     };
-    T::from_biguint(&pack::<T>(&sign_c, &exp_c, &man_c))
+    trace.sticky =
+        sticky_bit(&trace.sig_a, exp_c - exp_a) || sticky_bit(&trace.sig_b, exp_c - exp_b);
+    trace.aligned_a = norm_a.clone();
+    trace.aligned_b = norm_b.clone();
+
+    // both signs are equal
+    normalize::<T>(sign_a, exp_c, norm_a + norm_b, trace)
 }
 
 fn effective_sub<T: FloatType>(
@@ -158,183 +247,187 @@ fn effective_sub<T: FloatType>(
     sign_b: BigUint,
     exp_b: BigUint,
     man_b: BigUint,
+    trace: &mut AddTrace,
 ) -> T {
     let zero = 0.to_biguint().unwrap();
-    let one = 1.to_biguint().unwrap();
-    let norm_bit = &one << (T::SIG - 1);
 
-    let (sign_c, exp_c, man_c) = if exp_a == exp_b {
-        // case 1: exponent equals
-        if exp_a == zero {
-            // case 1.1: subnormal/zero - subnormal/zero
-            let exp_c = zero.clone();
-            if man_a > man_b {
-                // |a| > |b|
-                let sign_c = sign_a;
-                let man_c = &man_a - &man_b;
-                (sign_c, exp_c, man_c)
-            } else if man_a < man_b {
-                // |a| < |b|
-                let sign_c = &one - sign_a;
-                let man_c = &man_b - &man_a;
-                (sign_c, exp_c, man_c)
-            } else {
-                // |a| == |b|
-                // +0
-                let sign_c = zero.clone();
-                let man_c = zero;
-                (sign_c, exp_c, man_c)
-            }
-        } else if exp_a == T::max_exp() {
-            // case 1.2: inf/nan - inf/nan
-            if man_a != zero {
-                // nan
-                (sign_a, exp_a, man_a)
-            } else if man_b != zero {
-                // nan
-                (sign_b, exp_b, man_b)
-            } else {
-                // inf - inf = nan
-                // signaling
-                (zero, T::max_exp(), one << (T::SIG - 2))
-            }
-        } else {
-            // case 1.3: normal - normal
-            if man_a < man_b {
-                // |a| < |b|
-                let sign_c = one - sign_a;
-                let mut man_c = man_b - man_a;
-                let man_diff = man_c.to_u64_digits()[0];
-                // shift=0 when clz=11([63:53])
-                let shift = man_diff.leading_zeros() - (64 - T::SIG) as u32;
-                let exp_c = exp_a - shift;
-                man_c <<= shift;
-                man_c -= norm_bit;
-                (sign_c, exp_c, man_c)
-            } else if man_a > man_b {
-                // |a| > |b|
-                let sign_c = sign_a;
-                let mut man_c = man_a - man_b;
-                let man_diff = man_c.to_u64_digits()[0];
-                // shift=0 when clz=11([63:53])
-                let shift = man_diff.leading_zeros() - (64 - T::SIG) as u32;
-                let exp_c = exp_a - shift;
-                man_c <<= shift;
-                man_c -= norm_bit;
-                (sign_c, exp_c, man_c)
-            } else {
-                // |a| == |b|
-                // res = +0 if rounding mode is not roundTowardNegative
-                let sign_c = zero.clone();
-                (sign_c, zero.clone(), zero.clone())
-            }
-        }
-    } else {
-        // case 2: exponent differs
-        if exp_a == T::max_exp() {
-            // inf/nan
+    trace.effective_sub = true;
+    if exp_a == T::max_exp() || exp_b == T::max_exp() {
+        // inf - x, nan and inf - inf are handled by the caller
+        let (sign_c, exp_c, man_c) = if exp_a == T::max_exp() {
+            // inf
             (sign_a, exp_a, man_a)
-        } else if exp_b == T::max_exp() {
-            // inf/nan
-            (sign_b, exp_b, man_b)
         } else {
-            // pre shift 3 bits for rounding
-            let mut norm_a = if exp_a == zero {
-                // subnormal
-                man_a.clone()
-            } else {
-                // normal
-                &man_a + &norm_bit
-            };
-            norm_a <<= 3;
-            let mut norm_b = if exp_b == zero {
-                // subnormal
-                man_b.clone()
-            } else {
-                // normal
-                &man_b + &norm_bit
-            };
-            norm_b <<= 3;
-
-            if exp_a > exp_b {
-                // |a| > |b|
-                let sign_c = sign_a;
-
-                // right shift with sticky bit
-                let exp_diff = (&exp_a - &exp_b).to_u64_digits().pop().unwrap_or(0);
-                let norm_b = rshift_sticky(&norm_b, exp_diff);
-                let mut man_c = &norm_a - &norm_b;
-
-                let man_diff = man_c.to_u64_digits()[0];
-                // shift=1 when clz=9([63:55])
-                let shift = man_diff.leading_zeros() + 3 - (64 - T::SIG) as u32;
-                man_c <<= shift;
-                let exp_c = &exp_a - shift;
-                man_c -= &norm_bit << 3;
-
-                // round pre shifted 3 bits
-                man_c = round(&man_c);
-
-                (sign_c, exp_c, man_c)
-            } else {
-                // |a| < |b|
-                let sign_c = &one - sign_a;
+            // inf
+            (sign_b, exp_b, man_b)
+        };
+        trace.special = true;
+        trace.result = pack::<T>(&sign_c, &exp_c, &man_c);
+        return T::from_biguint(&trace.result);
+    }
 
-                // right shift with sticky bit
-                let exp_diff = (&exp_b - &exp_a).to_u64_digits().pop().unwrap_or(0);
-                let norm_a = rshift_sticky(&norm_a, exp_diff);
-                let mut man_c = &norm_b - &norm_a;
+    let (exp_a, norm_a) = unpack::<T>(&exp_a, &man_a);
+    let (exp_b, norm_b) = unpack::<T>(&exp_b, &man_b);
+    trace.exp_a = exp_a;
+    trace.exp_b = exp_b;
+    trace.exp_diff = exp_a.abs_diff(exp_b);
+    trace.sig_a = norm_a.clone();
+    trace.sig_b = norm_b.clone();
+
+    if (exp_a, &norm_a) > (exp_b, &norm_b) {
+        // |a| > |b|
+        // right shift with sticky bit
+        let aligned_b = rshift_sticky(&norm_b, exp_a - exp_b);
+        trace.sticky = sticky_bit(&norm_b, exp_a - exp_b);
+        trace.aligned_a = norm_a.clone();
+        trace.aligned_b = aligned_b.clone();
+        normalize::<T>(sign_a, exp_a, norm_a - aligned_b, trace)
+    } else if (exp_a, &norm_a) < (exp_b, &norm_b) {
+        // |a| < |b|
+        // right shift with sticky bit
+        let aligned_a = rshift_sticky(&norm_a, exp_b - exp_a);
+        trace.sticky = sticky_bit(&norm_a, exp_b - exp_a);
+        trace.aligned_a = aligned_a.clone();
+        trace.aligned_b = norm_b.clone();
+        normalize::<T>(sign_b, exp_b, norm_b - aligned_a, trace)
+    } else {
+        // |a| == |b|
+        // res = +0 if rounding mode is not roundTowardNegative
+        trace.aligned_a = norm_a;
+        trace.aligned_b = norm_b;
+        let sign = (trace.rounding == RoundingMode::TowardNegative) as u32;
+        trace.result = pack::<T>(&sign.to_biguint().unwrap(), &zero, &zero);
+        T::from_biguint(&trace.result)
+    }
+}
 
-                let man_diff = man_c.to_u64_digits()[0];
-                // shift=1 when clz=9([63:55])
-                let shift = man_diff.leading_zeros() + 3 - (64 - T::SIG) as u32;
-                man_c <<= shift;
-                let exp_c = &exp_b - shift;
-                man_c -= &norm_bit << 3;
+pub fn softfloat_add<T: FloatType>(a: T, b: T, env: &FpEnv) -> (T, ExceptionFlags) {
+    let (res, trace) = softfloat_add_trace(a, b, env);
+    (res, trace.flags)
+}
 
-                // round pre shifted 3 bits
-                man_c = round(&man_c);
+pub fn softfloat_sub<T: FloatType>(a: T, b: T, env: &FpEnv) -> (T, ExceptionFlags) {
+    let (res, trace) = softfloat_sub_trace(a, b, env);
+    (res, trace.flags)
+}
 
-                (sign_c, exp_c, man_c)
-            }
-        }
+// nan operands and inf - inf skip the datapath
+fn nan_trace<T: FloatType>(a: T, b: T, negate_b: bool, env: &FpEnv) -> Option<(T, AddTrace)> {
+    let (sign_a, exp_a, man_a) = extract::<T>(&a.to_biguint());
+    let (sign_b, exp_b, man_b) = extract::<T>(&b.to_biguint());
+    let inf_a = exp_a == T::max_exp() && man_a.bits() == 0;
+    let inf_b = exp_b == T::max_exp() && man_b.bits() == 0;
+    let effective_sub = (sign_a != sign_b) != negate_b;
+    // signaling nan
+    let snan_a = exp_a == T::max_exp() && man_a.bits() != 0 && !man_a.bit(T::SIG as u64 - 2);
+    let snan_b = exp_b == T::max_exp() && man_b.bits() != 0 && !man_b.bit(T::SIG as u64 - 2);
+    let (res, invalid) = match propagate_nan(env.nan_policy, &[a, b]) {
+        Some(res) => (res, snan_a || snan_b),
+        None if inf_a && inf_b && effective_sub => (invalid_nan(env.nan_policy), true),
+        None => return None,
     };
-    T::from_biguint(&pack::<T>(&sign_c, &exp_c, &man_c))
+    let trace = AddTrace {
+        effective_sub,
+        special: true,
+        result: res.to_biguint(),
+        rounding: env.rounding,
+        flags: ExceptionFlags {
+            invalid,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    Some((res, trace))
 }
 
-pub fn softfloat_add<T: FloatType>(a: T, b: T) -> T {
+// a + b, or a - b if negate_b
+fn add_sub_trace<T: FloatType>(a: T, b: T, negate_b: bool, env: &FpEnv) -> (T, AddTrace) {
+    let mut input_flags = ExceptionFlags::default();
+    let a = flush_input(a, env, &mut input_flags);
+    let b = flush_input(b, env, &mut input_flags);
+    // the sign of a nan b is not flipped
+    if let Some((res, mut trace)) = nan_trace(a, b, negate_b, env) {
+        trace.flags |= input_flags;
+        return (res, trace);
+    }
     let one = 1.to_biguint().unwrap();
     let num_a = a.to_biguint();
     let (sign_a, exp_a, man_a) = extract::<T>(&num_a);
     let num_b = b.to_biguint();
     let (sign_b, exp_b, man_b) = extract::<T>(&num_b);
-    if (&sign_a ^ &sign_b) == one {
+    // a - b = a + (-b)
+    let sign_b = if negate_b { &one - sign_b } else { sign_b };
+    let mut trace = AddTrace {
+        rounding: env.rounding,
+        ..Default::default()
+    };
+    let res: T = if (&sign_a ^ &sign_b) == one {
         // sub
-        effective_sub(sign_a, exp_a, man_a, sign_b, exp_b, man_b)
+        effective_sub(sign_a, exp_a, man_a, sign_b, exp_b, man_b, &mut trace)
     } else {
         // add
-        effective_add(sign_a, exp_a, man_a, sign_b, exp_b, man_b)
-    }
+        effective_add(sign_a, exp_a, man_a, sign_b, exp_b, man_b, &mut trace)
+    };
+    // subnormal results are the only tiny ones
+    let (_, exp_c, man_c) = extract::<T>(&trace.result);
+    let tiny = exp_c.bits() == 0 && man_c.bits() != 0;
+    let res = flush_output(res, tiny, env, &mut trace.flags);
+    trace.result = res.to_biguint();
+    trace.flags |= input_flags;
+    (res, trace)
 }
 
-pub fn softfloat_sub<T: FloatType>(a: T, b: T) -> T {
-    let one = 1.to_biguint().unwrap();
-    let num_a = a.to_biguint();
-    let (sign_a, exp_a, man_a) = extract::<T>(&num_a);
-    let num_b = b.to_biguint();
-    let (sign_b, exp_b, man_b) = extract::<T>(&num_b);
-    if (&sign_a ^ &sign_b) == one {
-        // add
-        effective_add(sign_a, exp_a, man_a, sign_b, exp_b, man_b)
+pub fn softfloat_add_trace<T: FloatType>(a: T, b: T, env: &FpEnv) -> (T, AddTrace) {
+    add_sub_trace(a, b, false, env)
+}
+
+pub fn softfloat_sub_trace<T: FloatType>(a: T, b: T, env: &FpEnv) -> (T, AddTrace) {
+    add_sub_trace(a, b, true, env)
+}
+
+pub fn print_add_trace<T: FloatType>(trace: &AddTrace) -> String {
+    let mut res = String::new();
+    let op = if trace.effective_sub { "sub" } else { "add" };
+    if trace.special {
+        res += &format!("effective {}: inf/nan operand\n", op);
     } else {
-        // sub
-        effective_sub(sign_a, exp_a, man_a, sign_b, exp_b, man_b)
+        // implicit bit, fraction, guard/round/sticky and one bit for carry
+        let width = T::SIG + 4;
+        res += &format!("effective {}\n", op);
+        res += &format!(
+            "exp_a={},exp_b={},exp_diff={}\n",
+            trace.exp_a, trace.exp_b, trace.exp_diff
+        );
+        res += &format!("sig_a={:0width$b}\n", trace.sig_a, width = width);
+        res += &format!("sig_b={:0width$b}\n", trace.sig_b, width = width);
+        res += &format!("aligned_a={:0width$b}\n", trace.aligned_a, width = width);
+        res += &format!("aligned_b={:0width$b}\n", trace.aligned_b, width = width);
+        res += &format!("sticky={}\n", trace.sticky as u8);
+        res += &format!("sum={:0width$b}\n", trace.sum, width = width);
+        res += &format!("norm_shift={}\n", trace.norm_shift);
+        res += &format!("norm={:0width$b}\n", trace.norm, width = width);
+        res += &format!(
+            "guard={},round={},sticky={},round_up={}\n",
+            trace.guard as u8, trace.round as u8, trace.sticky_round as u8, trace.round_up as u8
+        );
+        res += &format!("exp_c={}\n", trace.exp_c);
     }
+    res += &format!(
+        "result={:#x}({})",
+        trace.result,
+        print_float::<T>(&trace.result)
+    );
+    res
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{print_float, softfloat_add, softfloat_sub, FloatType};
+    use crate::{
+        from_hardfloat, hardfloat_add, hardfloat_sub, print_add_trace, print_float, softfloat_add,
+        softfloat_add_trace, softfloat_sub, softfloat_sub_trace, to_hardfloat, BigFloat, FloatType,
+        FpEnv, RoundingMode,
+    };
+    use half::bf16;
 
     // Macro for NaN-aware float comparison
     // NaN values can have different sign bits in IEEE 754, so we only check
@@ -361,7 +454,8 @@ mod tests {
 
     #[test]
     fn test() {
-        for (a, b) in vec![
+        let env = FpEnv::default();
+        for (a, b) in [
             // normal + normal
             (1.0, 1.1),
             (1.0, 2.0),
@@ -371,7 +465,8 @@ mod tests {
             (4503599627370496.0, 0.4),
             (4503599627370496.0, 0.5),
             (4503599627370496.0, 0.6),
-            // (1.5E+308, 1.5E+308),
+            (1.5E+308, 1.5E+308),
+            (f64::MAX, -f64::MAX),
             // subnormal/zero + normal
             (0.0, 0.1),
             (1.0 / 1.5E+308, 0.1),
@@ -393,7 +488,7 @@ mod tests {
             println!("b={}({})", b, print_float::<f64>(&b.to_biguint()));
 
             let a_plus_b = a + b;
-            let soft_a_plus_b = softfloat_add(a, b);
+            let soft_a_plus_b = softfloat_add(a, b, &env).0;
             println!(
                 "a+b={}({})",
                 a_plus_b,
@@ -407,7 +502,7 @@ mod tests {
             assert_float_eq!(a_plus_b, soft_a_plus_b);
 
             let b_plus_a = b + a;
-            let soft_b_plus_a = softfloat_add(b, a);
+            let soft_b_plus_a = softfloat_add(b, a, &env).0;
             println!(
                 "b+a={}({})",
                 b_plus_a,
@@ -421,7 +516,7 @@ mod tests {
             assert_float_eq!(b_plus_a, soft_b_plus_a);
 
             let a_minus_b = a - b;
-            let soft_a_minus_b = softfloat_sub(a, b);
+            let soft_a_minus_b = softfloat_sub(a, b, &env).0;
             println!(
                 "a-b={}({})",
                 a_minus_b,
@@ -435,7 +530,7 @@ mod tests {
             assert_float_eq!(a_minus_b, soft_a_minus_b);
 
             let b_minus_a = b - a;
-            let soft_b_minus_a = softfloat_sub(b, a);
+            let soft_b_minus_a = softfloat_sub(b, a, &env).0;
             println!(
                 "b-a={}({})",
                 b_minus_a,
@@ -449,4 +544,150 @@ mod tests {
             assert_float_eq!(b_minus_a, soft_b_minus_a);
         }
     }
+
+    #[test]
+    fn test_trace() {
+        let env = FpEnv::default();
+        // 1.0 + 1.5*2^-24: guard and round bits set, round up
+        let (res, trace) = softfloat_add_trace(1.0f32, 1.5 * f32::EPSILON / 2.0, &env);
+        assert_eq!(res, 1.0 + f32::EPSILON);
+        assert!(!trace.effective_sub && !trace.special);
+        assert_eq!((trace.exp_a, trace.exp_b, trace.exp_diff), (127, 103, 24));
+        assert_eq!(trace.aligned_b, 6u32.into());
+        assert!(!trace.sticky);
+        assert_eq!(trace.norm_shift, 0);
+        assert!(trace.guard && trace.round && !trace.sticky_round && trace.round_up);
+
+        // bits shifted away by alignment set the sticky bit
+        let (_, trace) = softfloat_add_trace(1.0f32, 1.25 * f32::EPSILON / 8.0, &env);
+        assert!(trace.sticky && trace.sticky_round && !trace.round_up);
+
+        // carry out of addition
+        let (res, trace) = softfloat_add_trace(1.5f32, 1.5, &env);
+        assert_eq!(res, 3.0);
+        assert_eq!(trace.norm_shift, -1);
+
+        // cancellation
+        let (res, trace) = softfloat_sub_trace(1.0f32, 0.75, &env);
+        assert_eq!(res, 0.25);
+        assert!(trace.effective_sub);
+        assert_eq!(trace.norm_shift, 2);
+        assert_eq!(trace.exp_c, 125);
+
+        let (_, trace) = softfloat_sub_trace(f32::INFINITY, 1.0, &env);
+        assert!(trace.special);
+        assert!(print_add_trace::<f32>(&trace).starts_with("effective sub: inf/nan operand"));
+    }
+
+    #[test]
+    fn test_rounding() {
+        // bits and flags match the HardFloat model in every rounding mode
+        let mut state = 0x2545f4914f6cdd1du64;
+        for _ in 0..2000 {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let a = f32::from_bits(state as u32);
+            // small exponents for subnormal sums, large ones for overflow
+            let exp_b = match state >> 62 {
+                0 => (state >> 56) as u32 % 3,
+                1 => 254,
+                _ => (state >> 56) as u32,
+            };
+            let b = f32::from_bits(((state >> 32) as u32 & 0x807fffff) | (exp_b << 23));
+            let a = if state & 1 == 0 {
+                a
+            } else {
+                f32::from_bits(a.to_bits() & 0x81ffffff)
+            };
+            for rm in [
+                RoundingMode::NearestEven,
+                RoundingMode::NearestMaxMag,
+                RoundingMode::TowardZero,
+                RoundingMode::TowardPositive,
+                RoundingMode::TowardNegative,
+            ] {
+                let env = FpEnv {
+                    rounding: rm,
+                    ..Default::default()
+                };
+                for (sub, (res, trace)) in [
+                    (false, softfloat_add_trace(a, b, &env)),
+                    (true, softfloat_sub_trace(a, b, &env)),
+                ] {
+                    let (expected, flags) = if sub {
+                        hardfloat_sub::<f32>(
+                            &to_hardfloat::<f32>(&a.to_biguint()),
+                            &to_hardfloat::<f32>(&b.to_biguint()),
+                            &env,
+                        )
+                    } else {
+                        hardfloat_add::<f32>(
+                            &to_hardfloat::<f32>(&a.to_biguint()),
+                            &to_hardfloat::<f32>(&b.to_biguint()),
+                            &env,
+                        )
+                    };
+                    let expected = f32::from_biguint(&from_hardfloat::<f32>(&expected));
+                    assert_float_eq!(res, expected);
+                    assert_eq!(trace.flags, flags, "{} {} {:?} {}", a, b, rm, sub);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_stochastic() {
+        // same as rounding the exact sum with the same random bits
+        let mut state = 0x2545f4914f6cdd1du64;
+        for _ in 0..2000 {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let a = f32::from_bits(state as u32 & 0xbfffffff);
+            // exponent differences beyond the guard, round and sticky bits
+            let exp_b = (a.to_bits() >> 23 & 0x7f) as u64 + (state >> 58) % 40;
+            let b =
+                f32::from_bits((state >> 32) as u32 & 0x807fffff | (exp_b.min(254) as u32) << 23);
+            let env = FpEnv {
+                rounding: RoundingMode::Stochastic(state.rotate_left(29)),
+                ..Default::default()
+            };
+            let res = softfloat_add(a, b, &env).0;
+            // f32 sums are exact in 128 bits
+            let exact = |x: f32| BigFloat::from_float(x).unwrap();
+            let (sum, _) = exact(a).add(&exact(b), 128, RoundingMode::NearestEven);
+            if sum.is_zero() {
+                continue;
+            }
+            let (expected, _) = sum.to_float::<f32>(&env);
+            assert_eq!(res.to_bits(), expected.to_bits(), "{} {}", a, b);
+        }
+
+        // accumulating 0.001 in bf16 stalls at 1 with round to nearest,
+        // while stochastic rounding keeps the expected value
+        let step = bf16::from_f64(0.001);
+        let exact = 1.0 + 2000.0 * step.to_f64();
+        let mut nearest = bf16::ONE;
+        let mut stochastic = bf16::ONE;
+        for _ in 0..2000 {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            nearest = softfloat_add(nearest, step, &FpEnv::default()).0;
+            let env = FpEnv {
+                rounding: RoundingMode::Stochastic(state),
+                ..Default::default()
+            };
+            stochastic = softfloat_add(stochastic, step, &env).0;
+        }
+        assert_eq!(nearest, bf16::ONE);
+        // the standard deviation is about 0.15
+        assert!(
+            (stochastic.to_f64() - exact).abs() < 0.6,
+            "{} {}",
+            stochastic,
+            exact
+        );
+    }
 }
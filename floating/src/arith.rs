@@ -0,0 +1,337 @@
+use num_bigint::BigUint;
+
+use crate::{
+    decode, extract, flush_input, invalid_nan, propagate_nan, BigFloat, ExceptionFlags, FloatType,
+    FpEnv, RoundingMode,
+};
+
+// multiplication, division and square root through exact BigFloat arithmetic
+
+// (is nan, is signaling, is inf, is zero)
+fn class<T: FloatType>(a: T) -> (bool, bool, bool, bool) {
+    let (_, exp, man) = extract::<T>(&a.to_biguint());
+    let max = exp == T::max_exp();
+    let nan = max && man.bits() != 0;
+    let signaling = nan && !man.bit(T::SIG as u64 - 2);
+    (
+        nan,
+        signaling,
+        max && !nan,
+        exp.bits() == 0 && man.bits() == 0,
+    )
+}
+
+fn signed_inf<T: FloatType>(sign: bool) -> T {
+    let sign_bit = BigUint::from(sign as u32) << (T::WIDTH - 1);
+    T::from_biguint(&(sign_bit | (T::max_exp() << (T::SIG - 1))))
+}
+
+fn signed_zero<T: FloatType>(sign: bool) -> T {
+    T::from_biguint(&(BigUint::from(sign as u32) << (T::WIDTH - 1)))
+}
+
+// nan operands propagate, a signaling one raises invalid
+fn nan_operands<T: FloatType>(operands: &[T], env: &FpEnv) -> Option<(T, ExceptionFlags)> {
+    let res = propagate_nan(env.nan_policy, operands)?;
+    let flags = ExceptionFlags {
+        invalid: operands.iter().any(|&a| class(a).1),
+        ..Default::default()
+    };
+    Some((res, flags))
+}
+
+fn invalid<T: FloatType>(env: &FpEnv, flags: ExceptionFlags) -> (T, ExceptionFlags) {
+    let flags = ExceptionFlags {
+        invalid: true,
+        ..flags
+    };
+    (invalid_nan(env.nan_policy), flags)
+}
+
+// keep an inexact quotient or root from looking exact or like a midpoint:
+// truncate to SIG + 64 bits and set a sticky bit (round to odd),
+// the 64 bits below the rounding position are also what stochastic rounding uses
+fn round_to_odd(res: Option<(BigFloat, bool)>) -> BigFloat {
+    let (res, inexact) = res.unwrap();
+    if !inexact {
+        return res;
+    }
+    let sig = (res.sig() << 1u32) | BigUint::from(1u32);
+    BigFloat::new(res.sign(), sig, res.exp() - 1)
+}
+
+// round the exact result to T with FTZ
+fn finish<T: FloatType>(res: &BigFloat, env: &FpEnv, flags: ExceptionFlags) -> (T, ExceptionFlags) {
+    let (res, round_flags) = res.to_float::<T>(env);
+    (res, flags | round_flags)
+}
+
+fn exact<T: FloatType>(a: T) -> BigFloat {
+    let (sign, exp, sig) = decode::<T>(&a.to_biguint());
+    BigFloat::new(sign, sig, exp)
+}
+
+pub fn softfloat_mul<T: FloatType>(a: T, b: T, env: &FpEnv) -> (T, ExceptionFlags) {
+    let mut flags = ExceptionFlags::default();
+    let a = flush_input(a, env, &mut flags);
+    let b = flush_input(b, env, &mut flags);
+    if let Some(res) = nan_operands(&[a, b], env) {
+        return res;
+    }
+    let (_, _, inf_a, zero_a) = class(a);
+    let (_, _, inf_b, zero_b) = class(b);
+    let (a, b) = (exact(a), exact(b));
+    let sign = a.sign() != b.sign();
+    if (inf_a && zero_b) || (zero_a && inf_b) {
+        return invalid(env, flags);
+    }
+    if inf_a || inf_b {
+        return (signed_inf(sign), flags);
+    }
+    // the product of two significands is exact in 2 SIG bits
+    let (res, _) = a.mul(&b, 2 * T::SIG, RoundingMode::TowardZero);
+    finish(&res, env, flags)
+}
+
+pub fn softfloat_div<T: FloatType>(a: T, b: T, env: &FpEnv) -> (T, ExceptionFlags) {
+    let mut flags = ExceptionFlags::default();
+    let a = flush_input(a, env, &mut flags);
+    let b = flush_input(b, env, &mut flags);
+    if let Some(res) = nan_operands(&[a, b], env) {
+        return res;
+    }
+    let (_, _, inf_a, zero_a) = class(a);
+    let (_, _, inf_b, zero_b) = class(b);
+    let (a, b) = (exact(a), exact(b));
+    let sign = a.sign() != b.sign();
+    if (inf_a && inf_b) || (zero_a && zero_b) {
+        return invalid(env, flags);
+    }
+    if inf_a {
+        return (signed_inf(sign), flags);
+    }
+    if zero_b {
+        flags.div_by_zero = true;
+        return (signed_inf(sign), flags);
+    }
+    if inf_b || zero_a {
+        return (signed_zero(sign), flags);
+    }
+    let res = round_to_odd(a.div(&b, T::SIG + 64, RoundingMode::TowardZero));
+    finish(&res, env, flags)
+}
+
+// sqrt(-0) = -0, other negative numbers are invalid
+pub fn softfloat_sqrt<T: FloatType>(a: T, env: &FpEnv) -> (T, ExceptionFlags) {
+    let mut flags = ExceptionFlags::default();
+    let a = flush_input(a, env, &mut flags);
+    if let Some(res) = nan_operands(&[a], env) {
+        return res;
+    }
+    let (_, _, inf, zero) = class(a);
+    let a_exact = exact(a);
+    if zero {
+        return (a, flags);
+    }
+    if a_exact.sign() {
+        return invalid(env, flags);
+    }
+    if inf {
+        return (a, flags);
+    }
+    let res = round_to_odd(a_exact.sqrt(T::SIG + 64, RoundingMode::TowardZero));
+    finish(&res, env, flags)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        format_info, from_hardfloat, hardfloat_mul, softfloat_convert, softfloat_div,
+        softfloat_mul, softfloat_sqrt, to_hardfloat, CustomFloat, FloatType, FpEnv, RoundingMode,
+        Tininess, CUSTOM_MAX_EXP,
+    };
+    use half::f16;
+
+    const MODES: [RoundingMode; 5] = [
+        RoundingMode::NearestEven,
+        RoundingMode::NearestMaxMag,
+        RoundingMode::TowardZero,
+        RoundingMode::TowardPositive,
+        RoundingMode::TowardNegative,
+    ];
+
+    #[test]
+    fn test() {
+        let env = FpEnv::default();
+        let mut state = 0x2545f4914f6cdd1du64;
+        for _ in 0..10000 {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let a = f64::from_bits(state);
+            let b = f64::from_bits(state.rotate_left(23));
+            // exponents around the f32 range, with subnormal and overflowing results
+            let c = f32::from_bits((state >> 32) as u32 & 0x87ffffff | 0x30000000);
+            let d = f32::from_bits(state as u32 & 0x87ffffff | 0x30000000);
+            for (res, expected) in [
+                (softfloat_mul(a, b, &env).0, a * b),
+                (softfloat_div(a, b, &env).0, a / b),
+                (softfloat_sqrt(a, &env).0, a.sqrt()),
+            ] {
+                if expected.is_nan() {
+                    assert!(res.is_nan());
+                } else {
+                    assert_eq!(res.to_bits(), expected.to_bits(), "{} {}", a, b);
+                }
+            }
+            for (res, expected) in [
+                (softfloat_mul(c, d, &env).0, c * d),
+                (softfloat_div(c, d, &env).0, c / d),
+                (softfloat_sqrt(c, &env).0, c.sqrt()),
+            ] {
+                if expected.is_nan() {
+                    assert!(res.is_nan());
+                } else {
+                    assert_eq!(res.to_bits(), expected.to_bits(), "{} {}", c, d);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_rounding() {
+        let mut state = 0x2545f4914f6cdd1du64;
+        for _ in 0..5000 {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let a = f32::from_bits((state >> 32) as u32);
+            let b = f32::from_bits(state as u32);
+            // f16 quotients and roots are never within 2^-53 of an f16 boundary
+            // unless they are on it, so rounding the f64 result is exact
+            let c = f16::from_bits((state >> 48) as u16);
+            let d = f16::from_bits(state as u16);
+            for rm in MODES {
+                let env = FpEnv {
+                    rounding: rm,
+                    tininess: Tininess::AfterRounding,
+                    ..Default::default()
+                };
+                let (res, flags) = softfloat_mul(a, b, &env);
+                let (expected, expected_flags) = hardfloat_mul::<f32>(
+                    &to_hardfloat::<f32>(&a.to_bits().into()),
+                    &to_hardfloat::<f32>(&b.to_bits().into()),
+                    &env,
+                );
+                let expected = from_hardfloat::<f32>(&expected);
+                if res.is_nan() {
+                    assert!(f32::from_bits(u32::try_from(&expected).unwrap()).is_nan());
+                } else {
+                    assert_eq!(
+                        res.to_bits(),
+                        u32::try_from(&expected).unwrap(),
+                        "{} {}",
+                        a,
+                        b
+                    );
+                }
+                assert_eq!(flags, expected_flags, "{} {} {:?}", a, b, rm);
+
+                let wide = (c.to_f64(), d.to_f64());
+                for ((res, flags), exact) in [
+                    (softfloat_div(c, d, &env), wide.0 / wide.1),
+                    (softfloat_sqrt(c, &env), wide.0.sqrt()),
+                ] {
+                    let (expected, mut expected_flags) = softfloat_convert::<f64, f16>(exact, &env);
+                    if exact.is_infinite() && !c.is_infinite() {
+                        // division by zero
+                        expected_flags.div_by_zero = true;
+                    }
+                    if exact.is_nan() {
+                        // invalid operations are checked in test_special
+                        assert!(res.is_nan());
+                        continue;
+                    }
+                    assert_eq!(res.to_bits(), expected.to_bits(), "{} {} {:?}", c, d, rm);
+                    assert_eq!(flags, expected_flags, "{} {} {:?}", c, d, rm);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_special() {
+        let env = FpEnv::default();
+        let inf = f32::INFINITY;
+        let snan = f32::from_bits(0x7f800001);
+        for ((res, flags), expected, expected_flags) in [
+            (softfloat_mul(inf, 0.0, &env), f32::NAN, 0b10000),
+            (softfloat_mul(-inf, 2.0, &env), -inf, 0b00000),
+            (softfloat_mul(-0.0, 2.0, &env), -0.0, 0b00000),
+            (softfloat_mul(f32::MAX, 2.0, &env), inf, 0b00101),
+            (
+                softfloat_mul(f32::MIN_POSITIVE, 0.5, &env),
+                f32::MIN_POSITIVE / 2.0,
+                0b00000,
+            ),
+            (
+                softfloat_mul(f32::from_bits(3), 0.5, &env),
+                f32::from_bits(2),
+                0b00011,
+            ),
+            (softfloat_mul(snan, 1.0, &env), f32::NAN, 0b10000),
+            (softfloat_div(1.0, -0.0, &env), -inf, 0b01000),
+            (softfloat_div(0.0, 0.0, &env), f32::NAN, 0b10000),
+            (softfloat_div(inf, inf, &env), f32::NAN, 0b10000),
+            (softfloat_div(-1.0, inf, &env), -0.0, 0b00000),
+            (softfloat_div(1.0, 3.0, &env), 1.0 / 3.0, 0b00001),
+            (softfloat_div(f32::NAN, 0.0, &env), f32::NAN, 0b00000),
+            (softfloat_sqrt(-0.0, &env), -0.0, 0b00000),
+            (softfloat_sqrt(-1.0, &env), f32::NAN, 0b10000),
+            (softfloat_sqrt(inf, &env), inf, 0b00000),
+            (softfloat_sqrt(2.0, &env), 2f32.sqrt(), 0b00001),
+            (
+                softfloat_sqrt(f32::from_bits(1), &env),
+                f32::from_bits(1).sqrt(),
+                0b00001,
+            ),
+        ] {
+            if expected.is_nan() {
+                assert!(res.is_nan());
+            } else {
+                assert_eq!(res.to_bits(), expected.to_bits(), "{}", expected);
+            }
+            assert_eq!(flags.to_bits(), expected_flags, "{}", expected);
+        }
+
+        // subnormal results are flushed with FTZ
+        let ftz = FpEnv {
+            ftz: true,
+            ..Default::default()
+        };
+        let (res, flags) = softfloat_mul(f32::MIN_POSITIVE, 0.5, &ftz);
+        assert_eq!((res.to_bits(), flags.to_bits()), (0, 0b00011));
+        let (res, flags) = softfloat_div(-f32::MIN_POSITIVE, 4.0, &ftz);
+        assert_eq!((res.to_bits(), flags.to_bits()), (0x80000000, 0b00011));
+
+        // the widest exponent of CustomFloat
+        type Wide = CustomFloat<CUSTOM_MAX_EXP, 108>;
+        let info = format_info::<Wide>();
+        let (max, tiny) = (
+            Wide::from_biguint(&info.max_finite.bits),
+            Wide::from_biguint(&info.min_subnormal.unwrap().bits),
+        );
+        let (res, flags) = softfloat_mul(max, max, &env);
+        assert_eq!(
+            (res.to_biguint(), flags.to_bits()),
+            (Wide::max_exp() << 107, 0b00101)
+        );
+        let (res, flags) = softfloat_mul(tiny, tiny, &env);
+        assert_eq!((res, flags.to_bits()), (CustomFloat(0), 0b00011));
+        let (res, flags) = softfloat_div(tiny, max, &env);
+        assert_eq!((res, flags.to_bits()), (CustomFloat(0), 0b00011));
+        let (res, flags) = softfloat_sqrt(tiny, &env);
+        assert_eq!(flags.to_bits(), 0b00001);
+        assert_eq!(softfloat_mul(res, res, &env).0, tiny);
+    }
+}
@@ -0,0 +1,492 @@
+use num_bigint::{BigInt, BigUint, Sign};
+use std::cmp::Ordering;
+use std::fmt::Display;
+
+use crate::{
+    decode, extract, print_dyadic, round_at, round_env, ExceptionFlags, FloatType, FpEnv,
+    RoundingMode,
+};
+
+// (-1)^sign * sig * 2^exp with an unbounded significand and exponent
+// operations round to a precision in bits chosen at runtime, like MPFR
+// there is no inf or nan, those operations return None instead
+// sig has no trailing zeros so every value has a single representation,
+// zeros keep their sign but compare equal
+#[derive(Clone, Debug)]
+pub struct BigFloat {
+    sign: bool,
+    sig: BigUint,
+    exp: i64,
+}
+
+// discarded bits kept by div and sqrt, enough for stochastic rounding
+const GUARD: usize = 66;
+
+impl BigFloat {
+    pub fn new(sign: bool, sig: BigUint, exp: i64) -> BigFloat {
+        match sig.trailing_zeros() {
+            Some(zeros) => BigFloat {
+                sign,
+                sig: sig >> zeros,
+                exp: exp + zeros as i64,
+            },
+            None => BigFloat::zero(sign),
+        }
+    }
+
+    pub fn zero(sign: bool) -> BigFloat {
+        BigFloat {
+            sign,
+            sig: BigUint::default(),
+            exp: 0,
+        }
+    }
+
+    pub fn from_int(int: &BigInt) -> BigFloat {
+        BigFloat::new(int.sign() == Sign::Minus, int.magnitude().clone(), 0)
+    }
+
+    // exact value of a finite float, None for inf and nan
+    pub fn from_float<T: FloatType>(a: T) -> Option<BigFloat> {
+        let num = a.to_biguint();
+        let (_, exp, _) = extract::<T>(&num);
+        if exp == T::max_exp() {
+            return None;
+        }
+        let (sign, exp, sig) = decode::<T>(&num);
+        Some(BigFloat::new(sign, sig, exp))
+    }
+
+    // round to T in the rounding mode of env, overflowing to inf
+    // and underflowing to subnormal numbers and zero, or to zero with FTZ
+    pub fn to_float<T: FloatType>(&self, env: &FpEnv) -> (T, ExceptionFlags) {
+        round_env::<T>(self.sign, self.exp, &self.sig, env)
+    }
+
+    pub fn sign(&self) -> bool {
+        self.sign
+    }
+
+    pub fn sig(&self) -> &BigUint {
+        &self.sig
+    }
+
+    pub fn exp(&self) -> i64 {
+        self.exp
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.sig.bits() == 0
+    }
+
+    // number of significant bits
+    pub fn prec(&self) -> usize {
+        self.sig.bits() as usize
+    }
+
+    // weight of the bit above the msb: |self| < 2^top
+    fn top(&self) -> i64 {
+        self.exp + self.sig.bits() as i64
+    }
+
+    pub fn neg(&self) -> BigFloat {
+        BigFloat {
+            sign: !self.sign,
+            ..self.clone()
+        }
+    }
+
+    pub fn abs(&self) -> BigFloat {
+        BigFloat {
+            sign: false,
+            ..self.clone()
+        }
+    }
+
+    // round to prec bits, returns the result and inexact
+    pub fn round(&self, prec: usize, rm: RoundingMode) -> (BigFloat, bool) {
+        assert!(prec >= 1, "precision must be at least 1 bit");
+        if self.prec() <= prec {
+            return (self.clone(), false);
+        }
+        let lsb = self.top() - prec as i64;
+        let (sig, inexact) = round_at(self.sign, &self.sig, self.exp, lsb, rm);
+        (BigFloat::new(self.sign, sig, lsb), inexact)
+    }
+
+    pub fn add(&self, other: &BigFloat, prec: usize, rm: RoundingMode) -> (BigFloat, bool) {
+        if self.is_zero() && other.is_zero() {
+            // -0 + -0 is the only exact zero sum that keeps a negative sign by default
+            let sign = if self.sign == other.sign {
+                self.sign
+            } else {
+                rm == RoundingMode::TowardNegative
+            };
+            return (BigFloat::zero(sign), false);
+        }
+        if self.is_zero() || other.is_zero() {
+            let res = if self.is_zero() { other } else { self };
+            return res.round(prec, rm);
+        }
+
+        let (large, small) = if self.top() >= other.top() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        // a small operand below both the lsb of large and the 64 bits after
+        // its rounding position, which stochastic rounding reads, only acts
+        // as a sticky bit, replace it by a small power of two
+        // so that 1 + 2^-1000000 does not take a million bits
+        let rounding = large.top().saturating_sub(prec as i64);
+        let limit = large.exp.min(rounding.saturating_sub(GUARD as i64));
+        let small = if small.top() < limit {
+            BigFloat::new(small.sign, BigUint::from(1u32), limit - 2)
+        } else {
+            small.clone()
+        };
+
+        let exp = large.exp.min(small.exp);
+        let to_int = |a: &BigFloat| {
+            let sign = if a.sign { Sign::Minus } else { Sign::Plus };
+            BigInt::from_biguint(sign, &a.sig << (a.exp - exp) as usize)
+        };
+        let sum = to_int(large) + to_int(&small);
+        if sum.sign() == Sign::NoSign {
+            return (BigFloat::zero(rm == RoundingMode::TowardNegative), false);
+        }
+        BigFloat::new(sum.sign() == Sign::Minus, sum.magnitude().clone(), exp).round(prec, rm)
+    }
+
+    pub fn sub(&self, other: &BigFloat, prec: usize, rm: RoundingMode) -> (BigFloat, bool) {
+        self.add(&other.neg(), prec, rm)
+    }
+
+    pub fn mul(&self, other: &BigFloat, prec: usize, rm: RoundingMode) -> (BigFloat, bool) {
+        let sign = self.sign != other.sign;
+        BigFloat::new(sign, &self.sig * &other.sig, self.exp + other.exp).round(prec, rm)
+    }
+
+    // None when dividing by zero
+    pub fn div(&self, other: &BigFloat, prec: usize, rm: RoundingMode) -> Option<(BigFloat, bool)> {
+        if other.is_zero() {
+            return None;
+        }
+        let sign = self.sign != other.sign;
+        if self.is_zero() {
+            return Some((BigFloat::zero(sign), false));
+        }
+        // a quotient of at least prec + GUARD bits, and a sticky bit for the remainder
+        let shift = (prec + GUARD + other.prec()).saturating_sub(self.prec());
+        let num = &self.sig << shift;
+        let quotient = &num / &other.sig;
+        let exact = &quotient * &other.sig == num;
+        let exp = self.exp - other.exp - shift as i64;
+        let res = BigFloat::new(
+            sign,
+            (quotient << 1) | BigUint::from(!exact as u32),
+            exp - 1,
+        );
+        Some(res.round(prec, rm))
+    }
+
+    // None for negative numbers, sqrt(-0) = -0
+    pub fn sqrt(&self, prec: usize, rm: RoundingMode) -> Option<(BigFloat, bool)> {
+        if self.is_zero() {
+            return Some((self.clone(), false));
+        }
+        if self.sign {
+            return None;
+        }
+        // a root of at least prec + GUARD bits from an even exponent
+        let mut shift = (2 * (prec + GUARD)).saturating_sub(self.prec()) as i64;
+        if (self.exp - shift) % 2 != 0 {
+            shift += 1;
+        }
+        let num = &self.sig << shift as usize;
+        let root = num.sqrt();
+        let exact = &root * &root == num;
+        let exp = (self.exp - shift) / 2;
+        let res = BigFloat::new(false, (root << 1) | BigUint::from(!exact as u32), exp - 1);
+        Some(res.round(prec, rm))
+    }
+}
+
+impl PartialEq for BigFloat {
+    fn eq(&self, other: &BigFloat) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for BigFloat {}
+
+impl PartialOrd for BigFloat {
+    fn partial_cmp(&self, other: &BigFloat) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// numeric order, -0 == +0
+impl Ord for BigFloat {
+    fn cmp(&self, other: &BigFloat) -> Ordering {
+        let signum = |a: &BigFloat| match (a.is_zero(), a.sign) {
+            (true, _) => 0,
+            (false, true) => -1,
+            (false, false) => 1,
+        };
+        let (sign_a, sign_b) = (signum(self), signum(other));
+        if sign_a != sign_b || sign_a == 0 {
+            return sign_a.cmp(&sign_b);
+        }
+        let exp = self.exp.min(other.exp);
+        let magnitude = (self.top().cmp(&other.top())).then_with(|| {
+            let a = &self.sig << (self.exp - exp) as usize;
+            let b = &other.sig << (other.exp - exp) as usize;
+            a.cmp(&b)
+        });
+        if sign_a < 0 {
+            magnitude.reverse()
+        } else {
+            magnitude
+        }
+    }
+}
+
+impl Display for BigFloat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.sign {
+            write!(f, "-")?;
+        }
+        write!(f, "{}", print_dyadic(&self.sig, self.exp))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{softfloat_convert, BigFloat, FloatType, FpEnv, RoundingMode};
+    use num_bigint::{BigInt, BigUint};
+
+    const MODES: [RoundingMode; 5] = [
+        RoundingMode::NearestEven,
+        RoundingMode::NearestMaxMag,
+        RoundingMode::TowardZero,
+        RoundingMode::TowardPositive,
+        RoundingMode::TowardNegative,
+    ];
+
+    fn big<T: FloatType>(a: T) -> BigFloat {
+        BigFloat::from_float(a).unwrap()
+    }
+
+    fn to_f64(a: &BigFloat, rm: RoundingMode) -> f64 {
+        let env = FpEnv {
+            rounding: rm,
+            ..Default::default()
+        };
+        a.to_float::<f64>(&env).0
+    }
+
+    #[test]
+    fn test() {
+        let rne = RoundingMode::NearestEven;
+        let mut state = 0x2545f4914f6cdd1du64;
+        for _ in 0..10000 {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            // exponents around 2^-100 to 2^100, away from overflow and subnormal results
+            let exp = |bits: u64| (bits & 0x800fffffffffffff) | ((923 + (bits >> 52) % 200) << 52);
+            let a = f64::from_bits(exp(state));
+            let b = f64::from_bits(exp(state.rotate_left(17) ^ 0x5555));
+            let (big_a, big_b) = (big(a), big(b));
+
+            // precision 53 rounds like f64 itself
+            let (res, _) = big_a.add(&big_b, 53, rne);
+            assert_eq!(
+                to_f64(&res, rne).to_bits(),
+                (a + b).to_bits(),
+                "{} {}",
+                a,
+                b
+            );
+            let (res, _) = big_a.sub(&big_b, 53, rne);
+            assert_eq!(
+                to_f64(&res, rne).to_bits(),
+                (a - b).to_bits(),
+                "{} {}",
+                a,
+                b
+            );
+            let (res, inexact) = big_a.mul(&big_b, 53, rne);
+            assert_eq!(
+                to_f64(&res, rne).to_bits(),
+                (a * b).to_bits(),
+                "{} {}",
+                a,
+                b
+            );
+            assert_eq!(inexact, big_a.mul(&big_b, 106, rne).0 != res);
+            let (res, _) = big_a.div(&big_b, 53, rne).unwrap();
+            assert_eq!(
+                to_f64(&res, rne).to_bits(),
+                (a / b).to_bits(),
+                "{} {}",
+                a,
+                b
+            );
+            let (res, _) = big_a.abs().sqrt(53, rne).unwrap();
+            assert_eq!(
+                to_f64(&res, rne).to_bits(),
+                a.abs().sqrt().to_bits(),
+                "{}",
+                a
+            );
+
+            // products of f32 are exact in f64, so every mode rounds like a conversion
+            let (c, d) = (a as f32, b as f32);
+            let exact = c as f64 * d as f64;
+            if !(f32::MIN_POSITIVE as f64..=f32::MAX as f64).contains(&exact.abs()) {
+                continue;
+            }
+            for rm in MODES {
+                let (res, inexact) = big(c).mul(&big(d), 24, rm);
+                let env = FpEnv {
+                    rounding: rm,
+                    ..Default::default()
+                };
+                let (expected, flags) = softfloat_convert::<f64, f32>(exact, &env);
+                assert_eq!(to_f64(&res, rne), expected as f64, "{} {} {:?}", c, d, rm);
+                assert_eq!(inexact, flags.inexact);
+            }
+        }
+    }
+
+    #[test]
+    fn test_rounding() {
+        // directed quotients and roots bracket the exact result
+        let mut state = 0x2545f4914f6cdd1du64;
+        for _ in 0..2000 {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let a = BigFloat::new(state >> 63 != 0, BigUint::from(state >> 1), -40);
+            let b = BigFloat::new(false, BigUint::from(state.rotate_left(29) | 1), -20);
+            for prec in [1, 7, 64, 200] {
+                let down = RoundingMode::TowardNegative;
+                let up = RoundingMode::TowardPositive;
+                let (lo, lo_inexact) = a.div(&b, prec, down).unwrap();
+                let (hi, hi_inexact) = a.div(&b, prec, up).unwrap();
+                let exact = |q: &BigFloat| q.mul(&b, usize::MAX >> 1, down).0;
+                assert!(exact(&lo) <= a && a <= exact(&hi));
+                assert_eq!(lo_inexact, hi_inexact);
+                assert_eq!(lo_inexact, lo != hi);
+                assert!(lo.prec() <= prec && hi.prec() <= prec);
+
+                let (lo, _) = b.sqrt(prec, down).unwrap();
+                let (hi, inexact) = b.sqrt(prec, up).unwrap();
+                let square = |r: &BigFloat| r.mul(r, usize::MAX >> 1, down).0;
+                assert!(square(&lo) <= b && b <= square(&hi));
+                assert_eq!(inexact, lo != hi);
+                let (nearest, _) = b.sqrt(prec, RoundingMode::NearestEven).unwrap();
+                assert!(nearest == lo || nearest == hi);
+            }
+        }
+    }
+
+    #[test]
+    fn test_stochastic() {
+        // 1 + 2^-30 in 24 bits is 1 + ulp/128, rounding up 1/128 of the time
+        let one = BigFloat::from_int(&BigInt::from(1));
+        let small = BigFloat::new(false, BigUint::from(1u32), -30);
+        let n = 20000u64;
+        let mut ups = 0;
+        let mut state = 0x2545f4914f6cdd1du64;
+        for _ in 0..n {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let (res, inexact) = one.add(&small, 24, RoundingMode::Stochastic(state));
+            assert!(inexact);
+            let res = to_f64(&res, RoundingMode::NearestEven);
+            if res == 1.0 + f32::EPSILON as f64 {
+                ups += 1;
+            } else {
+                assert_eq!(res, 1.0);
+            }
+        }
+        // unbiased within 5 sigma
+        let p = 1.0 / 128.0;
+        let sigma = (n as f64 * p * (1.0 - p)).sqrt();
+        assert!((ups as f64 - n as f64 * p).abs() <= 5.0 * sigma, "{}", ups);
+    }
+
+    #[test]
+    fn test_special() {
+        let rne = RoundingMode::NearestEven;
+        let one = BigFloat::from_int(&BigInt::from(1));
+        let tiny = BigFloat::new(false, BigUint::from(1u32), -1000000);
+
+        // the far operand only acts as a sticky bit
+        let (res, inexact) = one.add(&tiny, 53, rne);
+        assert_eq!((res.clone(), inexact), (one.clone(), true));
+        let (res, _) = one.add(&tiny, 53, RoundingMode::TowardPositive);
+        assert_eq!(to_f64(&res, rne), 1.0 + f64::EPSILON);
+        let (res, _) = one.sub(&tiny, 53, RoundingMode::TowardZero);
+        assert_eq!(to_f64(&res, rne), 1.0 - f64::EPSILON / 2.0);
+        let (res, inexact) = one.add(&tiny, 1000002, rne);
+        assert_eq!(res.prec(), 1000001);
+        assert!(!inexact);
+
+        // zeros
+        let zero = BigFloat::zero(false);
+        let neg_zero = BigFloat::zero(true);
+        assert_eq!(zero, neg_zero);
+        let (res, _) = one.sub(&one, 10, rne);
+        assert!(res.is_zero() && !res.sign());
+        let (res, _) = one.sub(&one, 10, RoundingMode::TowardNegative);
+        assert!(res.is_zero() && res.sign());
+        let (res, _) = neg_zero.add(&neg_zero, 10, rne);
+        assert!(res.sign());
+        let (res, _) = neg_zero.sqrt(10, rne).unwrap();
+        assert!(res.is_zero() && res.sign());
+        assert!(one.div(&zero, 10, rne).is_none());
+        assert!(one.neg().sqrt(10, rne).is_none());
+
+        // exact results and conversions
+        let three = BigFloat::from_int(&BigInt::from(-3));
+        let (res, inexact) = three.mul(&three, 10, rne);
+        assert_eq!(
+            (res, inexact),
+            (BigFloat::from_int(&BigInt::from(9)), false)
+        );
+        let (res, inexact) = one.div(&three, 10, rne).unwrap();
+        assert!(inexact && res.sign());
+        assert_eq!(to_f64(&res, rne), -0.33349609375);
+        assert_eq!(three.to_string(), "-3");
+        assert_eq!(BigFloat::from_float(0.375f32).unwrap().to_string(), "0.375");
+        assert!(BigFloat::from_float(f32::INFINITY).is_none());
+        assert!(BigFloat::from_float(f32::NAN).is_none());
+        let min = big(f64::from_bits(1));
+        assert_eq!((min.sig().clone(), min.exp()), (BigUint::from(1u32), -1074));
+        // ties of the last place
+        let (res, _) = BigFloat::from_int(&BigInt::from(5)).round(2, rne);
+        assert_eq!(to_f64(&res, rne), 4.0);
+        let (res, _) = BigFloat::from_int(&BigInt::from(5)).round(2, RoundingMode::NearestMaxMag);
+        assert_eq!(to_f64(&res, rne), 6.0);
+
+        // to_float overflows and underflows like a conversion
+        let huge = BigFloat::new(true, BigUint::from(3u32), 5000);
+        let (res, flags) = huge.to_float::<f64>(&FpEnv::default());
+        assert_eq!((res, flags.to_bits()), (f64::NEG_INFINITY, 0b00101));
+        let mut env = FpEnv {
+            rounding: RoundingMode::TowardPositive,
+            ..Default::default()
+        };
+        let (res, flags) = tiny.to_float::<f32>(&env);
+        assert_eq!((res, flags.to_bits()), (f32::from_bits(1), 0b00011));
+        // and flushes tiny results with FTZ
+        env.ftz = true;
+        let (res, flags) = tiny.to_float::<f32>(&env);
+        assert_eq!((res, flags.to_bits()), (0.0, 0b00011));
+    }
+}
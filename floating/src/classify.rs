@@ -1,4 +1,5 @@
 use num_bigint::ToBigUint;
+use std::fmt::Display;
 use std::num::FpCategory;
 
 use crate::{extract, FloatType};
@@ -20,13 +21,90 @@ pub fn softfloat_classify<T: FloatType>(a: T) -> FpCategory {
     }
 }
 
+// the ten classes of IEEE class(), in the order of the RISC-V fclass bits
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FloatClass {
+    NegativeInfinity,
+    NegativeNormal,
+    NegativeSubnormal,
+    NegativeZero,
+    PositiveZero,
+    PositiveSubnormal,
+    PositiveNormal,
+    PositiveInfinity,
+    SignalingNan,
+    QuietNan,
+}
+
+impl FloatClass {
+    // one-hot mask written by RISC-V fclass
+    pub fn to_fclass(self) -> u16 {
+        1 << (self as u16)
+    }
+
+    // categories of x86 VFPCLASS that match, as imm8 bits:
+    // 0 QNaN, 1 +0, 2 -0, 3 +inf, 4 -inf, 5 denormal, 6 negative finite, 7 SNaN
+    // negative finite excludes -0, like FinNeg of the SDM
+    pub fn to_vfpclass(self) -> u8 {
+        match self {
+            FloatClass::QuietNan => 1 << 0,
+            FloatClass::PositiveZero => 1 << 1,
+            FloatClass::NegativeZero => 1 << 2,
+            FloatClass::PositiveInfinity => 1 << 3,
+            FloatClass::NegativeInfinity => 1 << 4,
+            FloatClass::PositiveSubnormal => 1 << 5,
+            FloatClass::NegativeSubnormal => (1 << 5) | (1 << 6),
+            FloatClass::NegativeNormal => 1 << 6,
+            FloatClass::PositiveNormal => 0,
+            FloatClass::SignalingNan => 1 << 7,
+        }
+    }
+}
+
+impl Display for FloatClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            FloatClass::NegativeInfinity => "-inf",
+            FloatClass::NegativeNormal => "-normal",
+            FloatClass::NegativeSubnormal => "-subnormal",
+            FloatClass::NegativeZero => "-zero",
+            FloatClass::PositiveZero => "+zero",
+            FloatClass::PositiveSubnormal => "+subnormal",
+            FloatClass::PositiveNormal => "+normal",
+            FloatClass::PositiveInfinity => "+inf",
+            FloatClass::SignalingNan => "sNaN",
+            FloatClass::QuietNan => "qNaN",
+        })
+    }
+}
+
+pub fn softfloat_float_class<T: FloatType>(a: T) -> FloatClass {
+    let zero = 0.to_biguint().unwrap();
+    let num_a = a.to_biguint();
+    let (sign_a, _, man_a) = extract::<T>(&num_a);
+    let negative = sign_a != zero;
+    match (softfloat_classify(a), negative) {
+        (FpCategory::Nan, _) if man_a.bit(T::SIG as u64 - 2) => FloatClass::QuietNan,
+        (FpCategory::Nan, _) => FloatClass::SignalingNan,
+        (FpCategory::Infinite, true) => FloatClass::NegativeInfinity,
+        (FpCategory::Infinite, false) => FloatClass::PositiveInfinity,
+        (FpCategory::Normal, true) => FloatClass::NegativeNormal,
+        (FpCategory::Normal, false) => FloatClass::PositiveNormal,
+        (FpCategory::Subnormal, true) => FloatClass::NegativeSubnormal,
+        (FpCategory::Subnormal, false) => FloatClass::PositiveSubnormal,
+        (FpCategory::Zero, true) => FloatClass::NegativeZero,
+        (FpCategory::Zero, false) => FloatClass::PositiveZero,
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::softfloat_classify;
+    use crate::{softfloat_classify, softfloat_float_class, FloatClass};
+    use half::f16;
 
     #[test]
     fn test() {
-        for a in vec![
+        for a in [
             1.0,
             0.1,
             0.2,
@@ -40,4 +118,78 @@ mod tests {
             assert_eq!(cat, soft_cat);
         }
     }
+
+    #[test]
+    fn test_float_class() {
+        for (a, class, fclass, vfpclass) in [
+            (f32::NEG_INFINITY, FloatClass::NegativeInfinity, 0x001, 0x10),
+            (-1.0, FloatClass::NegativeNormal, 0x002, 0x40),
+            (
+                -f32::from_bits(1),
+                FloatClass::NegativeSubnormal,
+                0x004,
+                0x60,
+            ),
+            (-0.0, FloatClass::NegativeZero, 0x008, 0x04),
+            (0.0, FloatClass::PositiveZero, 0x010, 0x02),
+            (
+                f32::from_bits(0x007fffff),
+                FloatClass::PositiveSubnormal,
+                0x020,
+                0x20,
+            ),
+            (f32::MIN_POSITIVE, FloatClass::PositiveNormal, 0x040, 0x00),
+            (f32::INFINITY, FloatClass::PositiveInfinity, 0x080, 0x08),
+            (
+                f32::from_bits(0xff800001),
+                FloatClass::SignalingNan,
+                0x100,
+                0x80,
+            ),
+            (-f32::NAN, FloatClass::QuietNan, 0x200, 0x01),
+        ] {
+            let res = softfloat_float_class(a);
+            assert_eq!(res, class, "{}", a);
+            assert_eq!(res.to_fclass(), fclass);
+            assert_eq!(res.to_vfpclass(), vfpclass);
+        }
+
+        // classes agree with native predicates on every f16
+        for num in 0..=u16::MAX {
+            let a = f16::from_bits(num);
+            let class = softfloat_float_class(a);
+            let negative = a.is_sign_negative();
+            let expected = if a.is_nan() {
+                if num & 0x0200 != 0 {
+                    FloatClass::QuietNan
+                } else {
+                    FloatClass::SignalingNan
+                }
+            } else if a.is_infinite() {
+                if negative {
+                    FloatClass::NegativeInfinity
+                } else {
+                    FloatClass::PositiveInfinity
+                }
+            } else if a.is_normal() {
+                if negative {
+                    FloatClass::NegativeNormal
+                } else {
+                    FloatClass::PositiveNormal
+                }
+            } else if a.to_f32() == 0.0 {
+                if negative {
+                    FloatClass::NegativeZero
+                } else {
+                    FloatClass::PositiveZero
+                }
+            } else if negative {
+                FloatClass::NegativeSubnormal
+            } else {
+                FloatClass::PositiveSubnormal
+            };
+            assert_eq!(class, expected, "{:#x}", num);
+            assert_eq!(class.to_fclass().count_ones(), 1);
+        }
+    }
 }
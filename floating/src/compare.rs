@@ -0,0 +1,292 @@
+use num_bigint::{BigUint, ToBigUint};
+use std::cmp::Ordering;
+
+use crate::{flush_input, propagate_nan, ExceptionFlags, FloatType, FpEnv};
+
+// (sign, magnitude, is nan, is signaling nan)
+fn unpack<T: FloatType>(a: T) -> (bool, BigUint, bool, bool) {
+    let one = 1.to_biguint().unwrap();
+    let num = a.to_biguint();
+    let sign = num.bit(T::WIDTH as u64 - 1);
+    let magnitude = num & ((&one << (T::WIDTH - 1)) - &one);
+    let is_nan = magnitude > (T::max_exp() << (T::SIG - 1));
+    let is_signaling = is_nan && !magnitude.bit(T::SIG as u64 - 2);
+    (sign, magnitude, is_nan, is_signaling)
+}
+
+// IEEE comparison, None if unordered
+// quiet comparisons only raise invalid for signaling nan,
+// signaling comparisons raise invalid for any nan
+pub fn softfloat_compare<T: FloatType>(
+    a: T,
+    b: T,
+    signaling: bool,
+    env: &FpEnv,
+) -> (Option<Ordering>, ExceptionFlags) {
+    let mut flags = ExceptionFlags::default();
+    let a = flush_input(a, env, &mut flags);
+    let b = flush_input(b, env, &mut flags);
+    let (sign_a, mag_a, nan_a, snan_a) = unpack(a);
+    let (sign_b, mag_b, nan_b, snan_b) = unpack(b);
+    let unordered = nan_a || nan_b;
+    flags.invalid = snan_a || snan_b || (signaling && unordered);
+    if unordered {
+        return (None, flags);
+    }
+    let order = if mag_a.bits() == 0 && mag_b.bits() == 0 {
+        // +0 and -0 are equal
+        Ordering::Equal
+    } else {
+        match (sign_a, sign_b) {
+            (false, false) => mag_a.cmp(&mag_b),
+            (true, true) => mag_b.cmp(&mag_a),
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+        }
+    };
+    (Some(order), flags)
+}
+
+// compareQuietEqual like RISC-V feq
+pub fn softfloat_eq<T: FloatType>(a: T, b: T, env: &FpEnv) -> (bool, ExceptionFlags) {
+    let (order, flags) = softfloat_compare(a, b, false, env);
+    (order == Some(Ordering::Equal), flags)
+}
+
+// compareSignalingLess like RISC-V flt
+pub fn softfloat_lt<T: FloatType>(a: T, b: T, env: &FpEnv) -> (bool, ExceptionFlags) {
+    let (order, flags) = softfloat_compare(a, b, true, env);
+    (order == Some(Ordering::Less), flags)
+}
+
+// compareSignalingLessEqual like RISC-V fle
+pub fn softfloat_le<T: FloatType>(a: T, b: T, env: &FpEnv) -> (bool, ExceptionFlags) {
+    let (order, flags) = softfloat_compare(a, b, true, env);
+    (
+        matches!(order, Some(Ordering::Less | Ordering::Equal)),
+        flags,
+    )
+}
+
+// order of totalOrder: -nan < -inf < ... < -0 < +0 < ... < +inf < +nan
+// with nans ordered by payload and signaling before quiet
+pub fn total_cmp<T: FloatType>(a: T, b: T) -> Ordering {
+    let (sign_a, mag_a, _, _) = unpack(a);
+    let (sign_b, mag_b, _, _) = unpack(b);
+    match (sign_a, sign_b) {
+        (false, false) => mag_a.cmp(&mag_b),
+        (true, true) => mag_b.cmp(&mag_a),
+        // -0 goes below +0
+        (false, true) => Ordering::Greater,
+        (true, false) => Ordering::Less,
+    }
+}
+
+// IEEE totalOrder(a, b): a is not above b in total_cmp
+pub fn total_order<T: FloatType>(a: T, b: T) -> bool {
+    total_cmp(a, b) != Ordering::Greater
+}
+
+// shared part of the four min/max operations
+// number: a nan operand loses to a number
+fn min_max<T: FloatType>(a: T, b: T, max: bool, number: bool, env: &FpEnv) -> (T, ExceptionFlags) {
+    let mut flags = ExceptionFlags::default();
+    let a = flush_input(a, env, &mut flags);
+    let b = flush_input(b, env, &mut flags);
+    let (_, _, nan_a, snan_a) = unpack(a);
+    let (_, _, nan_b, snan_b) = unpack(b);
+    flags.invalid = snan_a || snan_b;
+    match (nan_a, nan_b) {
+        (true, false) if number => return (b, flags),
+        (false, true) if number => return (a, flags),
+        (false, false) => {}
+        _ => return (propagate_nan(env.nan_policy, &[a, b]).unwrap(), flags),
+    }
+    // -0 is below +0
+    let a_first = total_cmp(a, b) != Ordering::Greater;
+    (if a_first != max { a } else { b }, flags)
+}
+
+// IEEE 754-2019 minimum: nan propagates
+pub fn minimum<T: FloatType>(a: T, b: T, env: &FpEnv) -> (T, ExceptionFlags) {
+    min_max(a, b, false, false, env)
+}
+
+// IEEE 754-2019 maximum: nan propagates
+pub fn maximum<T: FloatType>(a: T, b: T, env: &FpEnv) -> (T, ExceptionFlags) {
+    min_max(a, b, true, false, env)
+}
+
+// IEEE 754-2019 minimumNumber: a number wins over any nan
+pub fn minimum_number<T: FloatType>(a: T, b: T, env: &FpEnv) -> (T, ExceptionFlags) {
+    min_max(a, b, false, true, env)
+}
+
+// IEEE 754-2019 maximumNumber: a number wins over any nan
+pub fn maximum_number<T: FloatType>(a: T, b: T, env: &FpEnv) -> (T, ExceptionFlags) {
+    min_max(a, b, true, true, env)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        maximum, maximum_number, minimum, minimum_number, softfloat_compare, softfloat_eq,
+        softfloat_le, softfloat_lt, total_cmp, total_order, FpEnv,
+    };
+    use half::f16;
+    use std::cmp::Ordering;
+
+    fn is_snan(a: f16) -> bool {
+        a.is_nan() && a.to_bits() & 0x0200 == 0
+    }
+
+    // reference on f64 where every f16 is exact
+    fn reference_min_max(a: f16, b: f16, max: bool, number: bool) -> Option<f16> {
+        if a.is_nan() || b.is_nan() {
+            return match (a.is_nan(), b.is_nan(), number) {
+                (true, false, true) => Some(b),
+                (false, true, true) => Some(a),
+                // some nan
+                _ => None,
+            };
+        }
+        let (x, y) = (a.to_f64(), b.to_f64());
+        if x == y {
+            // zeros of different signs
+            let neg = a.is_sign_negative() || b.is_sign_negative();
+            let pos = a.is_sign_positive() || b.is_sign_positive();
+            let pick_neg = if max { !pos } else { neg };
+            return Some(if pick_neg == a.is_sign_negative() {
+                a
+            } else {
+                b
+            });
+        }
+        Some(if (x < y) != max { a } else { b })
+    }
+
+    // every comparison and min/max of a pair against the f16 and f64 reference
+    fn check_pair(a: f16, b: f16, env: &FpEnv) {
+        let snan = is_snan(a) || is_snan(b);
+        let nan = a.is_nan() || b.is_nan();
+
+        let (order, flags) = softfloat_compare(a, b, false, env);
+        assert_eq!(order, a.partial_cmp(&b), "{:?} {:?}", a, b);
+        assert_eq!(flags.to_bits(), (snan as u8) << 4);
+        let (res, flags) = softfloat_eq(a, b, env);
+        assert_eq!(res, a == b);
+        assert_eq!(flags.invalid, snan);
+        let (res, flags) = softfloat_lt(a, b, env);
+        assert_eq!(res, a < b);
+        assert_eq!(flags.invalid, nan);
+        let (res, flags) = softfloat_le(a, b, env);
+        assert_eq!(res, a <= b);
+        assert_eq!(flags.invalid, nan);
+
+        assert_eq!(total_cmp(a, b), a.total_cmp(&b), "{:?} {:?}", a, b);
+        assert_eq!(total_order(a, b), a.total_cmp(&b) != Ordering::Greater);
+
+        for (op, max, number) in [
+            (minimum::<f16> as fn(f16, f16, &FpEnv) -> _, false, false),
+            (maximum, true, false),
+            (minimum_number, false, true),
+            (maximum_number, true, true),
+        ] {
+            let (res, flags) = op(a, b, env);
+            match reference_min_max(a, b, max, number) {
+                Some(expected) => {
+                    assert_eq!(res.to_bits(), expected.to_bits(), "{:?} {:?}", a, b)
+                }
+                None => {
+                    assert!(res.is_nan());
+                    assert!(!is_snan(res));
+                }
+            }
+            assert_eq!(flags.invalid, snan);
+        }
+    }
+
+    // a sampled pair set: every f16 against its negation, its neighbor encoding,
+    // a zero and a signaling nan in both orders
+    // test_f16_exhaustive covers all pairs
+    #[test]
+    fn test_f16_sampled() {
+        let env = FpEnv::default();
+        for num in 0..=u16::MAX {
+            let a = f16::from_bits(num);
+            let zero = f16::from_bits(0);
+            let snan = f16::from_bits(0x7d00);
+            let neg = f16::from_bits(num ^ 0x8000);
+            let neighbor = f16::from_bits(num ^ 1);
+            for (a, b) in [
+                (a, neg),
+                (a, neighbor),
+                (a, zero),
+                (zero, a),
+                (a, snan),
+                (snan, a),
+            ] {
+                check_pair(a, b, &env);
+            }
+        }
+    }
+
+    // all 2^32 pairs of f16, too slow for every run:
+    // cargo test --release -- --ignored test_f16_exhaustive
+    #[test]
+    #[ignore]
+    fn test_f16_exhaustive() {
+        let env = FpEnv::default();
+        for a in 0..=u16::MAX {
+            for b in 0..=u16::MAX {
+                check_pair(f16::from_bits(a), f16::from_bits(b), &env);
+            }
+        }
+    }
+
+    #[test]
+    fn test() {
+        let env = FpEnv::default();
+        // signed zeros
+        assert_eq!(minimum(0.0f32, -0.0, &env).0.to_bits(), (-0.0f32).to_bits());
+        assert_eq!(minimum(-0.0f32, 0.0, &env).0.to_bits(), (-0.0f32).to_bits());
+        assert_eq!(maximum(-0.0f32, 0.0, &env).0.to_bits(), 0.0f32.to_bits());
+        assert_eq!(
+            maximum_number(-0.0f32, 0.0, &env).0.to_bits(),
+            0.0f32.to_bits()
+        );
+        assert_eq!(softfloat_eq(0.0f32, -0.0, &env), (true, Default::default()));
+
+        // nan propagation and quieting
+        let snan = f32::from_bits(0x7f800001);
+        let (res, flags) = minimum(1.0, snan, &env);
+        assert_eq!((res.to_bits(), flags.to_bits()), (0x7fc00001, 0b10000));
+        let (res, flags) = minimum_number(1.0, snan, &env);
+        assert_eq!((res, flags.to_bits()), (1.0, 0b10000));
+        let (res, flags) = maximum_number(f32::NAN, -1.0, &env);
+        assert_eq!((res, flags.to_bits()), (-1.0, 0b00000));
+        let (res, flags) = maximum_number(f32::NAN, snan, &env);
+        assert!(res.is_nan());
+        assert_eq!(flags.to_bits(), 0b10000);
+
+        // against native f64
+        let mut state = 0x2545f4914f6cdd1du64;
+        for _ in 0..10000 {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let a = f64::from_bits(state);
+            let b = if state & 1 == 0 {
+                f64::from_bits(state.rotate_left(7))
+            } else {
+                -a
+            };
+            assert_eq!(softfloat_compare(a, b, false, &env).0, a.partial_cmp(&b));
+            assert_eq!(total_cmp(a, b), a.total_cmp(&b));
+            if !a.is_nan() && !b.is_nan() && a != b {
+                assert_eq!(minimum_number(a, b, &env).0, a.min(b));
+                assert_eq!(maximum_number(a, b, &env).0, a.max(b));
+            }
+        }
+    }
+}
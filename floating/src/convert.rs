@@ -0,0 +1,190 @@
+use num_bigint::{BigUint, ToBigUint};
+
+use crate::{decode, extract, fixed_nan, flush_input, round_env, ExceptionFlags, FloatType, FpEnv};
+
+// convert between any two formats, rounding when narrowing
+pub fn softfloat_convert<F: FloatType, T: FloatType>(a: F, env: &FpEnv) -> (T, ExceptionFlags) {
+    let zero = 0.to_biguint().unwrap();
+    let one = 1.to_biguint().unwrap();
+    let mut flags = ExceptionFlags::default();
+    let num_a = flush_input(a, env, &mut flags).to_biguint();
+    let (sign_a, exp_a, man_a) = extract::<F>(&num_a);
+
+    let bits = if exp_a == F::max_exp() {
+        let sign = sign_a << (T::WIDTH - 1);
+        if man_a == zero {
+            // inf
+            sign | (T::max_exp() << (T::SIG - 1))
+        } else {
+            // nan
+            // signaling nan raises invalid and is quieted,
+            // the payload is kept from the msb like x86 and ARM
+            flags.invalid = !man_a.bit(F::SIG as u64 - 2);
+            let payload: BigUint = if T::SIG >= F::SIG {
+                man_a << (T::SIG - F::SIG)
+            } else {
+                man_a >> (F::SIG - T::SIG)
+            };
+            match fixed_nan::<T>(env.nan_policy) {
+                Some(nan) => nan.to_biguint(),
+                None => sign | (T::max_exp() << (T::SIG - 1)) | payload | (one << (T::SIG - 2)),
+            }
+        }
+    } else {
+        let (sign, exp, sig) = decode::<F>(&num_a);
+        let (res, round_flags) = round_env::<T>(sign, exp, &sig, env);
+        flags |= round_flags;
+        res.to_biguint()
+    };
+    (T::from_biguint(&bits), flags)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{softfloat_convert, CustomFloat, FloatType, FpEnv, RoundingMode};
+    use half::{bf16, f16};
+
+    fn convert<F: FloatType, T: FloatType>(a: F, rm: RoundingMode) -> T {
+        let env = FpEnv {
+            rounding: rm,
+            ..Default::default()
+        };
+        softfloat_convert::<F, T>(a, &env).0
+    }
+
+    #[test]
+    fn test() {
+        let rne = RoundingMode::NearestEven;
+        let mut state = 0x2545f4914f6cdd1du64;
+        for _ in 0..20000 {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let a = f64::from_bits(state);
+            // exponents around the f16 and f32 ranges
+            let b =
+                f64::from_bits((state & 0x800fffffffffffff) | ((880 + (state >> 52) % 300) << 52));
+            for a in [a, b] {
+                let res: f32 = convert(a, rne);
+                if a.is_nan() {
+                    assert!(res.is_nan());
+                    continue;
+                }
+                assert_eq!(res.to_bits(), (a as f32).to_bits(), "{}", a);
+                let res: CustomFloat<8, 24> = convert(a, rne);
+                assert_eq!(res.0 as u32, (a as f32).to_bits(), "{}", a);
+
+                // widening is exact
+                let c = a as f32;
+                let res: f64 = convert(c, rne);
+                assert_eq!(res.to_bits(), (c as f64).to_bits());
+                let res: f16 = convert(c, rne);
+                assert_eq!(res.to_bits(), f16::from_f32(c).to_bits(), "{}", c);
+                let res: bf16 = convert(c, rne);
+                assert_eq!(res.to_bits(), bf16::from_f32(c).to_bits(), "{}", c);
+                let res: CustomFloat<15, 113> = convert(c, rne);
+                let back: f32 = convert(res, rne);
+                assert_eq!(back.to_bits(), c.to_bits());
+            }
+        }
+    }
+
+    #[test]
+    fn test_rounding() {
+        let env = |rounding| FpEnv {
+            rounding,
+            ..Default::default()
+        };
+        let x = 1.0 + f64::EPSILON;
+        for (rm, expected) in [
+            (RoundingMode::NearestEven, 1.0f32),
+            (RoundingMode::TowardZero, 1.0),
+            (RoundingMode::TowardPositive, 1.0 + f32::EPSILON),
+            (RoundingMode::TowardNegative, 1.0),
+            (RoundingMode::NearestMaxMag, 1.0),
+        ] {
+            let (res, flags) = softfloat_convert::<f64, f32>(x, &env(rm));
+            assert_eq!(res, expected);
+            assert_eq!(flags.to_bits(), 0b00001);
+            let (res, _) = softfloat_convert::<f64, f32>(-x, &env(rm));
+            let expected = match rm {
+                RoundingMode::TowardPositive => -1.0,
+                RoundingMode::TowardNegative => -1.0 - f32::EPSILON,
+                _ => -expected,
+            };
+            assert_eq!(res, expected);
+        }
+
+        // no double rounding through f32, which would tie to 6648
+        let (res, _) =
+            softfloat_convert::<f64, f16>(-6650.000207581323, &env(RoundingMode::NearestEven));
+        assert_eq!(res, f16::from_f32(-6652.0));
+
+        // overflow
+        let (res, flags) = softfloat_convert::<f64, f16>(1e10, &env(RoundingMode::NearestEven));
+        assert_eq!(res, f16::INFINITY);
+        assert_eq!(flags.to_bits(), 0b00101);
+        let (res, _) = softfloat_convert::<f64, f16>(1e10, &env(RoundingMode::TowardZero));
+        assert_eq!(res, f16::MAX);
+
+        // underflow
+        let (res, flags) = softfloat_convert::<f64, f32>(1e-300, &env(RoundingMode::NearestEven));
+        assert_eq!(res, 0.0);
+        assert_eq!(flags.to_bits(), 0b00011);
+        let (res, _) = softfloat_convert::<f64, f32>(-1e-300, &env(RoundingMode::TowardNegative));
+        assert_eq!(res, -f32::from_bits(1));
+
+        // signaling nan is quieted with its payload
+        let (res, flags) = softfloat_convert::<f32, f64>(
+            f32::from_bits(0xff800123),
+            &env(RoundingMode::NearestEven),
+        );
+        assert_eq!(res.to_bits(), 0xfff8002460000000);
+        assert_eq!(flags.to_bits(), 0b10000);
+        let (res, flags) = softfloat_convert::<f64, f16>(
+            f64::from_bits(0x7ff8000000000001),
+            &env(RoundingMode::NearestEven),
+        );
+        assert_eq!(res.to_bits(), 0x7e00);
+        assert_eq!(flags.to_bits(), 0b00000);
+    }
+
+    #[test]
+    fn test_stochastic() {
+        // the rounding error in ulps averages to zero over random inputs and bits,
+        // while rounding down is off by half an ulp on average
+        let n = 20000;
+        let mut state = 0x2545f4914f6cdd1du64;
+        let (mut stochastic, mut down) = (0.0, 0.0);
+        for _ in 0..n {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            // positive and negative values in the normal range of bf16
+            let a = f64::from_bits(state & 0x800fffffffffffff | (1000 + (state >> 52) % 40) << 52);
+            let ulp = 2f64.powi(a.abs().log2().floor() as i32 - 7);
+            let rounded: bf16 = convert(a, RoundingMode::Stochastic(state.rotate_left(31)));
+            stochastic += (rounded.to_f64() - a) / ulp;
+            let rounded: bf16 = convert(a, RoundingMode::TowardNegative);
+            down += (rounded.to_f64() - a) / ulp;
+        }
+        let mean = stochastic / n as f64;
+        // one draw has a standard deviation below 0.5 ulp
+        assert!(mean.abs() < 5.0 * 0.5 / (n as f64).sqrt(), "{}", mean);
+        assert!(down / (n as f64) < -0.4);
+
+        // a fixed value rounds up in proportion to its discarded fraction
+        let a = 1.0 + 0.3 * f64::from(bf16::EPSILON);
+        let ups = (0..n)
+            .filter(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                let rounded: bf16 = convert(a, RoundingMode::Stochastic(state));
+                rounded > bf16::ONE
+            })
+            .count();
+        let sigma = (n as f64 * 0.3 * 0.7).sqrt();
+        assert!((ups as f64 - 0.3 * n as f64).abs() < 5.0 * sigma, "{}", ups);
+    }
+}
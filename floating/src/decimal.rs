@@ -0,0 +1,435 @@
+use num_bigint::{BigInt, BigUint, Sign, ToBigUint};
+
+use crate::{decode, extract, fixed_nan, round_rational, ExceptionFlags, FloatType, FpEnv};
+
+// a decimal or hex float literal
+pub(crate) enum Literal {
+    Inf(bool),
+    Nan(bool),
+    // (sign, digits, exp) with value = (-1)^sign * digits * base^exp
+    // where base is 10 for decimal and 2 for hex float
+    Finite(bool, BigUint, i64),
+}
+
+// split "-1.25e-3" or "-0x1.4p-3" into sign, digits and exponent
+pub(crate) fn parse_literal(s: &str, hex: bool) -> Option<Literal> {
+    let (sign, s) = match s.as_bytes().first()? {
+        b'-' => (true, &s[1..]),
+        b'+' => (false, &s[1..]),
+        _ => (false, s),
+    };
+    let lower = s.to_ascii_lowercase();
+    if lower == "inf" || lower == "infinity" {
+        return Some(Literal::Inf(sign));
+    } else if lower == "nan" {
+        return Some(Literal::Nan(sign));
+    }
+
+    let (s, radix, exp_chars, digit_exp) = if hex {
+        let s = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X"))?;
+        (s, 16, ['p', 'P'], 4)
+    } else {
+        (s, 10, ['e', 'E'], 1)
+    };
+    let (mantissa, exp) = match s.find(exp_chars) {
+        Some(i) => (&s[..i], s[i + 1..].parse::<i64>().ok()?),
+        None => (s, 0),
+    };
+    let (int, fract) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if int.is_empty() && fract.is_empty() {
+        return None;
+    }
+    if !int.chars().chain(fract.chars()).all(|c| c.is_digit(radix)) {
+        return None;
+    }
+    let digits = format!("{}{}", int, fract);
+    let num = BigUint::parse_bytes(digits.as_bytes(), radix)?;
+    let exp = exp.checked_sub(digit_exp * fract.len() as i64)?;
+    Some(Literal::Finite(sign, num, exp))
+}
+
+// round a parsed literal to the format T
+// nan literals give the nan of a policy that always produces the same one
+pub(crate) fn round_literal<T: FloatType>(
+    literal: Literal,
+    hex: bool,
+    env: &FpEnv,
+) -> (T, ExceptionFlags) {
+    let one = 1.to_biguint().unwrap();
+    let (sign, num, exp) = match literal {
+        Literal::Inf(sign) | Literal::Nan(sign) => {
+            let sign_bit = (sign as u32).to_biguint().unwrap() << (T::WIDTH - 1);
+            let mut bits = sign_bit | (T::max_exp() << (T::SIG - 1));
+            if matches!(literal, Literal::Nan(_)) {
+                if let Some(nan) = fixed_nan::<T>(env.nan_policy) {
+                    return (nan, ExceptionFlags::default());
+                }
+                // default quiet nan
+                bits |= one << (T::SIG - 2);
+            }
+            return (T::from_biguint(&bits), ExceptionFlags::default());
+        }
+        Literal::Finite(sign, num, exp) => (sign, num, exp),
+    };
+    if num.bits() == 0 {
+        let sign_bit = (sign as u32).to_biguint().unwrap() << (T::WIDTH - 1);
+        return (T::from_biguint(&sign_bit), ExceptionFlags::default());
+    }
+
+    // beyond these bounds the result is infinity or rounds like any
+    // smaller nonzero number, so the exponent can be clamped
+    let bound = (1i64 << (T::EXP - 1)) + T::SIG as i64 + 2;
+    let len = if hex {
+        num.bits() as i64
+    } else {
+        num.to_str_radix(10).len() as i64
+    };
+    let exp = exp.clamp(-bound - len, bound);
+    let (num, den) = literal_to_rational(sign, num, exp, hex);
+    round_rational::<T>(&num, &den, env)
+}
+
+fn literal_to_rational(sign: bool, num: BigUint, exp: i64, hex: bool) -> (BigInt, BigUint) {
+    let base = if hex { 2u32 } else { 10 }.to_biguint().unwrap();
+    let sign = if sign { Sign::Minus } else { Sign::Plus };
+    if exp >= 0 {
+        let num = num * base.pow(exp as u32);
+        (BigInt::from_biguint(sign, num), 1.to_biguint().unwrap())
+    } else {
+        (BigInt::from_biguint(sign, num), base.pow((-exp) as u32))
+    }
+}
+
+// exact value of a finite literal as num / den,
+// None for inf, nan and exponents beyond +-65536
+pub(crate) fn literal_rational(literal: Literal, hex: bool) -> Option<(BigInt, BigUint)> {
+    match literal {
+        Literal::Finite(sign, num, exp) if exp.abs() <= 1 << 16 => {
+            Some(literal_to_rational(sign, num, exp, hex))
+        }
+        _ => None,
+    }
+}
+
+// parse a decimal string like "-1.25e-3", "inf" or "nan"
+// and round it directly to the format T
+pub fn parse_decimal<T: FloatType>(s: &str, env: &FpEnv) -> Option<(T, ExceptionFlags)> {
+    let literal = parse_literal(s, false)?;
+    Some(round_literal::<T>(literal, false, env))
+}
+
+// exact value of a decimal string as num / den
+pub fn decimal_to_rational(s: &str) -> Option<(BigInt, BigUint)> {
+    literal_rational(parse_literal(s, false)?, false)
+}
+
+// finite number as (sign, m, e) where value = (-1)^sign * m * 10^e
+// returns None for inf and nan
+fn to_decimal<T: FloatType>(bits: &BigUint) -> Option<(bool, BigUint, i64)> {
+    let (_, exp, _) = extract::<T>(bits);
+    if exp == T::max_exp() {
+        return None;
+    }
+    let (sign, exp, sig) = decode::<T>(bits);
+    let (m, e) = dyadic_to_decimal(sig, exp);
+    Some((sign, m, e))
+}
+
+// sig * 2^exp as m * 10^e
+fn dyadic_to_decimal(sig: BigUint, exp: i64) -> (BigUint, i64) {
+    if exp >= 0 {
+        (sig << exp as usize, 0)
+    } else {
+        // 2^-n = 5^n * 10^-n
+        let five = 5.to_biguint().unwrap();
+        (sig * five.pow((-exp) as u32), exp)
+    }
+}
+
+// exact decimal value of sig * 2^exp
+pub fn print_dyadic(sig: &BigUint, exp: i64) -> String {
+    let (m, e) = dyadic_to_decimal(sig.clone(), exp);
+    format_decimal(false, &m.to_str_radix(10), e)
+}
+
+// print digits * 10^exp without exponent like Rust does
+fn format_decimal(sign: bool, digits: &str, exp: i64) -> String {
+    let sign = if sign { "-" } else { "" };
+    let digits = digits.trim_start_matches('0');
+    if digits.is_empty() {
+        return format!("{}0", sign);
+    }
+    // trailing zeros of the fraction are not printed
+    let point = digits.len() as i64 + exp;
+    let (int, fract) = if point <= 0 {
+        ("0".to_string(), "0".repeat((-point) as usize) + digits)
+    } else if exp >= 0 {
+        (
+            digits.to_string() + &"0".repeat(exp as usize),
+            String::new(),
+        )
+    } else {
+        let (int, fract) = digits.split_at(point as usize);
+        (int.to_string(), fract.to_string())
+    };
+    let fract = fract.trim_end_matches('0');
+    if fract.is_empty() {
+        format!("{}{}", sign, int)
+    } else {
+        format!("{}{}.{}", sign, int, fract)
+    }
+}
+
+fn format_special<T: FloatType>(bits: &BigUint) -> String {
+    let (sign, _, man) = extract::<T>(bits);
+    if man.bits() != 0 {
+        "NaN".to_string()
+    } else if sign.bits() != 0 {
+        "-inf".to_string()
+    } else {
+        "inf".to_string()
+    }
+}
+
+// exact decimal value of the encoding,
+// every binary float has a finite decimal expansion
+pub fn print_exact_decimal<T: FloatType>(bits: &BigUint) -> String {
+    match to_decimal::<T>(bits) {
+        Some((sign, m, e)) => format_decimal(sign, &m.to_str_radix(10), e),
+        None => format_special::<T>(bits),
+    }
+}
+
+// shortest decimal that parses back to the same encoding of T
+// between two shortest candidates the closest one is picked,
+// and the larger magnitude on ties like Rust does
+pub fn print_shortest_decimal<T: FloatType>(bits: &BigUint) -> String {
+    let Some((sign, m, e)) = to_decimal::<T>(bits) else {
+        return format_special::<T>(bits);
+    };
+    let one = 1.to_biguint().unwrap();
+    let ten = 10.to_biguint().unwrap();
+    let digits = m.to_str_radix(10);
+    let len = digits.len();
+    let round_trip = |candidate: &BigUint, exp: i64| {
+        let s = format!("{}{}e{}", if sign { "-" } else { "" }, candidate, exp);
+        let (res, _) = parse_decimal::<T>(&s, &FpEnv::default()).unwrap();
+        res.to_biguint() == *bits
+    };
+    for p in 1..len {
+        // the closest p digit numbers below and above
+        let scale = ten.pow((len - p) as u32);
+        let exp = e + (len - p) as i64;
+        let down = &m / &scale;
+        let rem = &m - &down * &scale;
+        if rem.bits() == 0 {
+            // exact with p digits
+            return format_decimal(sign, &down.to_str_radix(10), exp);
+        }
+        let up = &down + &one;
+        let down_ok = round_trip(&down, exp);
+        let up_ok = round_trip(&up, exp);
+        let res = match (down_ok, up_ok) {
+            (true, true) => {
+                let twice: BigUint = &rem << 1;
+                if twice < scale {
+                    down
+                } else {
+                    up
+                }
+            }
+            (true, false) => down,
+            (false, true) => up,
+            (false, false) => continue,
+        };
+        return format_decimal(sign, &res.to_str_radix(10), exp);
+    }
+    format_decimal(sign, &digits, e)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        parse_decimal, print_exact_decimal, print_shortest_decimal, CustomFloat, FloatType, FpEnv,
+        NanPolicy, RoundingMode,
+    };
+    use half::f16;
+    use num_bigint::BigUint;
+
+    fn parse<T: FloatType>(s: &str) -> T {
+        parse_decimal::<T>(s, &FpEnv::default()).unwrap().0
+    }
+
+    #[test]
+    fn test() {
+        let mut state = 0x2545f4914f6cdd1du64;
+        for _ in 0..2000 {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let a = f64::from_bits(state);
+            if a.is_nan() {
+                continue;
+            }
+            // shortest and full digits of f64 and f32
+            for s in [
+                format!("{}", a),
+                format!("{:e}", a),
+                format!("{:.30e}", a),
+                format!("{}", a as f32),
+                format!("{:.20e}", a as f32),
+            ] {
+                assert_eq!(
+                    parse::<f64>(&s).to_bits(),
+                    s.parse::<f64>().unwrap().to_bits()
+                );
+                assert_eq!(
+                    parse::<f32>(&s).to_bits(),
+                    s.parse::<f32>().unwrap().to_bits()
+                );
+                let res: CustomFloat<11, 53> = parse(&s);
+                assert_eq!(res.0 as u64, s.parse::<f64>().unwrap().to_bits());
+            }
+        }
+
+        for (s, expected) in [
+            ("0", 0.0f64),
+            ("-0.0", -0.0),
+            ("+1", 1.0),
+            ("1.", 1.0),
+            (".5", 0.5),
+            ("00012.50e1", 125.0),
+            ("1E-2", 0.01),
+            ("inf", f64::INFINITY),
+            ("-Infinity", f64::NEG_INFINITY),
+            ("1e400", f64::INFINITY),
+            ("1e-400", 0.0),
+            ("1e99999999999", f64::INFINITY),
+            ("-1e-99999999999", -0.0),
+            ("4.9406564584124654e-324", f64::from_bits(1)),
+            ("2.4703282292062328e-324", f64::from_bits(1)),
+            ("2.4703282292062327e-324", 0.0),
+        ] {
+            assert_eq!(parse::<f64>(s).to_bits(), expected.to_bits(), "{}", s);
+        }
+        assert!(parse::<f64>("nan").is_nan());
+        for s in ["", "-", ".", "e5", "1e", "1.2.3", "0x10", "1,5", "--1"] {
+            assert!(
+                parse_decimal::<f64>(s, &FpEnv::default()).is_none(),
+                "{}",
+                s
+            );
+        }
+    }
+
+    #[test]
+    fn test_rounding() {
+        // halfway between two f16 numbers after rounding to f64
+        // 6650.000207581323 rounds to 6652, but 6650.0 ties to even 6648
+        assert_eq!(parse::<f16>("6650.000207581323"), f16::from_f32(6652.0));
+        assert_eq!(parse::<f16>("6650"), f16::from_f32(6648.0));
+        // f32 halfway point 1 + 2^-24, slightly above
+        assert_eq!(parse::<f32>("1.0000000596046447753906250"), 1.0);
+        assert_eq!(
+            parse::<f32>("1.0000000596046447753906251"),
+            1.0 + f32::EPSILON
+        );
+        // rounds to 1.0 as an f64 first
+        assert_eq!(
+            parse::<f32>("1.00000005960464477539062500000000001"),
+            1.0 + f32::EPSILON
+        );
+
+        // directed rounding and flags
+        let env = |rounding| FpEnv {
+            rounding,
+            ..Default::default()
+        };
+        let (res, flags) = parse_decimal::<f32>("0.1", &env(RoundingMode::TowardZero)).unwrap();
+        assert_eq!(res, f32::from_bits(0x3dcccccc));
+        assert_eq!(flags.to_bits(), 0b00001);
+        let (res, _) = parse_decimal::<f32>("0.1", &env(RoundingMode::TowardPositive)).unwrap();
+        assert_eq!(res, 0.1);
+        let (_, flags) = parse_decimal::<f32>("0.5", &env(RoundingMode::NearestEven)).unwrap();
+        assert_eq!(flags.to_bits(), 0b00000);
+        let (res, flags) = parse_decimal::<f16>("65520", &env(RoundingMode::NearestEven)).unwrap();
+        assert_eq!(res, f16::INFINITY);
+        assert_eq!(flags.to_bits(), 0b00101);
+        let (res, flags) = parse_decimal::<f16>("1e-8", &env(RoundingMode::NearestEven)).unwrap();
+        assert_eq!(res, f16::from_f32(0.0));
+        assert_eq!(flags.to_bits(), 0b00011);
+
+        // FTZ and nan policy of the environment
+        let mut env = FpEnv {
+            ftz: true,
+            ..Default::default()
+        };
+        let (res, flags) = parse_decimal::<f32>("-1e-40", &env).unwrap();
+        assert_eq!((res.to_bits(), flags.to_bits()), (0x80000000, 0b00011));
+        let (res, _) = parse_decimal::<f32>("-nan", &env).unwrap();
+        assert_eq!(res.to_bits(), 0xffc00000);
+        env.nan_policy = NanPolicy::Cuda;
+        let (res, _) = parse_decimal::<f32>("-nan", &env).unwrap();
+        assert_eq!(res.to_bits(), 0x7fffffff);
+    }
+
+    #[test]
+    fn test_print() {
+        let mut state = 0x2545f4914f6cdd1du64;
+        for _ in 0..2000 {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let a = f64::from_bits(state);
+            let b = a as f32;
+            assert_eq!(
+                print_shortest_decimal::<f64>(&a.to_biguint()),
+                format!("{}", a)
+            );
+            assert_eq!(
+                print_shortest_decimal::<f32>(&b.to_biguint()),
+                format!("{}", b)
+            );
+            let exact = format!("{:.1100}", a);
+            let exact = if exact.contains('.') {
+                exact.trim_end_matches('0').trim_end_matches('.')
+            } else {
+                &exact
+            };
+            assert_eq!(print_exact_decimal::<f64>(&a.to_biguint()), exact);
+        }
+
+        for (bits, shortest, exact) in [
+            (0x0000, "0", "0"),
+            (0x8000, "-0", "-0"),
+            (0x3c00, "1", "1"),
+            (0x3555, "0.3333", "0.333251953125"),
+            (0x7bff, "65500", "65504"),
+            (0x0001, "0.00000006", "0.000000059604644775390625"),
+            (0xfc00, "-inf", "-inf"),
+            (0x7e00, "NaN", "NaN"),
+        ] {
+            let bits = BigUint::from(bits as u32);
+            assert_eq!(print_shortest_decimal::<f16>(&bits), shortest);
+            assert_eq!(print_exact_decimal::<f16>(&bits), exact);
+        }
+
+        // every f16 round trips
+        for num in 0..=u16::MAX {
+            let num = f16::from_bits(num);
+            if num.is_nan() {
+                continue;
+            }
+            let s = print_shortest_decimal::<f16>(&num.to_biguint());
+            assert_eq!(parse::<f16>(&s).to_bits(), num.to_bits(), "{}", s);
+        }
+
+        // custom formats print in their own precision
+        let third: CustomFloat<5, 4> = parse("0.3333");
+        assert_eq!(
+            print_shortest_decimal::<CustomFloat<5, 4>>(&third.to_biguint()),
+            "0.34"
+        );
+        assert_eq!(format!("{}", third), "0.34");
+    }
+}
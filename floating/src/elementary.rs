@@ -0,0 +1,1016 @@
+use num_bigint::{BigInt, BigUint, Sign};
+
+use crate::{
+    decode, decode_nan, extract, invalid_nan, propagate_nan, round_pack, ExceptionFlags, FloatType,
+    FpEnv,
+};
+
+// correctly rounded elementary functions
+// each one is evaluated in fixed point with w fractional bits into an interval
+// around the exact result, and w doubles until both ends of the interval round
+// to the same number (Ziv's strategy)
+// exact results are detected up front since no precision separates them
+// from a rounding boundary
+// these are references for libm testing, DAZ/FTZ do not apply
+
+// [lo, hi] * 2^exp contains the exact result
+struct Interval {
+    lo: BigInt,
+    hi: BigInt,
+    exp: i64,
+}
+
+impl Interval {
+    // m * 2^exp with an error of at most err units
+    fn around(m: BigInt, err: u64, exp: i64) -> Interval {
+        Interval {
+            lo: &m - err,
+            hi: m + err,
+            exp,
+        }
+    }
+
+    fn neg(self) -> Interval {
+        Interval {
+            lo: -self.hi,
+            hi: -self.lo,
+            exp: self.exp,
+        }
+    }
+
+    // smallest interval containing both
+    fn hull(a: Interval, b: Interval) -> Interval {
+        let exp = a.exp.min(b.exp);
+        let (a_lo, a_hi) = (a.lo << (a.exp - exp), a.hi << (a.exp - exp));
+        let (b_lo, b_hi) = (b.lo << (b.exp - exp), b.hi << (b.exp - exp));
+        Interval {
+            lo: a_lo.min(b_lo),
+            hi: a_hi.max(b_hi),
+            exp,
+        }
+    }
+
+    // a / b in fixed point with w fractional bits, both at 2^-w
+    fn div(a: &Interval, b: &Interval, w: usize) -> Interval {
+        if b.lo.sign() != b.hi.sign() || b.lo.sign() == Sign::NoSign {
+            // b may be zero, this interval never rounds to a single number
+            let huge = BigInt::from(1) << (2 * w + 64);
+            return Interval {
+                lo: -huge.clone(),
+                hi: huge,
+                exp: -(w as i64),
+            };
+        }
+        let mut lo: Option<BigInt> = None;
+        let mut hi: Option<BigInt> = None;
+        for num in [&a.lo, &a.hi] {
+            for den in [&b.lo, &b.hi] {
+                let scaled = num << w;
+                let q_lo = div_floor(&scaled, den);
+                let q_hi = -div_floor(&-scaled, den);
+                lo = Some(lo.map_or(q_lo.clone(), |lo| lo.min(q_lo)));
+                hi = Some(hi.map_or(q_hi.clone(), |hi| hi.max(q_hi)));
+            }
+        }
+        Interval {
+            lo: lo.unwrap(),
+            hi: hi.unwrap(),
+            exp: -(w as i64),
+        }
+    }
+}
+
+// floor(a / b) for b != 0
+fn div_floor(a: &BigInt, b: &BigInt) -> BigInt {
+    let q = a / b;
+    if (a.sign() == Sign::Minus) != (b.sign() == Sign::Minus) && &q * b != *a {
+        q - 1
+    } else {
+        q
+    }
+}
+
+// floor(a * 2^shift)
+fn shift_floor(a: &BigInt, shift: i64) -> BigInt {
+    if shift >= 0 {
+        a << shift
+    } else {
+        div_floor(a, &(BigInt::from(1) << -shift))
+    }
+}
+
+// a * b / 2^w rounded toward zero, so that series terms reach zero
+fn mul_fixed(a: &BigInt, b: &BigInt, w: usize) -> BigInt {
+    (a * b) / (BigInt::from(1) << w)
+}
+
+// floor((-1)^sign * sig * 2^exp * 2^w)
+fn fixed(sign: bool, sig: &BigUint, exp: i64, w: usize) -> BigInt {
+    let sign = if sign { Sign::Minus } else { Sign::Plus };
+    shift_floor(&BigInt::from_biguint(sign, sig.clone()), exp + w as i64)
+}
+
+// ln(2) * 2^w within 2 units: sum of 1 / (k 2^k)
+fn ln2(w: usize) -> BigInt {
+    let guard = 64 - (w as u64).leading_zeros() as usize + 4;
+    let prec = w + guard;
+    let one = BigUint::from(1u32) << prec;
+    let mut sum = BigUint::default();
+    for k in 1..=prec {
+        sum += (&one >> k) / k;
+    }
+    BigInt::from(sum >> guard)
+}
+
+// atan(1 / n) * 2^prec within 1 unit per term
+fn atan_inv(n: u32, prec: usize) -> BigInt {
+    let n2 = BigUint::from(n) * n;
+    let mut power = (BigUint::from(1u32) << prec) / n;
+    let mut sum = BigInt::default();
+    let mut i = 0u64;
+    while power.bits() != 0 {
+        let term = BigInt::from(&power / (2 * i + 1));
+        if i.is_multiple_of(2) {
+            sum += term;
+        } else {
+            sum -= term;
+        }
+        power /= &n2;
+        i += 1;
+    }
+    sum
+}
+
+// pi * 2^w within 2 units: 16 atan(1/5) - 4 atan(1/239)
+fn pi(w: usize) -> BigInt {
+    let guard = 64 - (w as u64).leading_zeros() as usize + 12;
+    let a = atan_inv(5, w + guard);
+    let b = atan_inv(239, w + guard);
+    shift_floor(&(a * 16 - b * 4), -(guard as i64))
+}
+
+// exp(x) for x = X * 2^-w
+fn exp_fixed(x: &BigInt, w: usize) -> Interval {
+    // x = k ln(2) + r with |r| <= ln(2) / 2
+    let coarse = ln2(w);
+    let k = div_floor(&((x << 1) + &coarse), &(&coarse << 1));
+    let guard = k.bits() as i64 + 4;
+    let fine = ln2(w + guard as usize);
+    let r = shift_floor(&((x << guard) - &k * fine), -guard);
+    // r is within 2 units
+
+    // Taylor series, each term is within 3 units
+    let one = BigInt::from(1) << w;
+    let mut sum = one.clone();
+    let mut term = one;
+    let mut n = 0u64;
+    loop {
+        n += 1;
+        term = mul_fixed(&term, &r, w) / n;
+        if term.sign() == Sign::NoSign {
+            break;
+        }
+        sum += &term;
+    }
+    let k: i64 = k.try_into().unwrap();
+    Interval::around(sum, 4 * n + 8, k - w as i64)
+}
+
+// ln(x) = k ln(2) + ln(m) for x = sig * 2^exp > 0,
+// returns k and ln(m) * 2^w with m in [0.75, 1.5) and its error
+fn log_parts(sig: &BigUint, exp: i64, w: usize) -> (i64, BigInt, u64) {
+    let bits = sig.bits() as i64;
+    // m = sig / 2^shift
+    let mut shift = bits - 1;
+    if bits >= 2 && *sig >= BigUint::from(3u32) << (bits - 2) {
+        shift += 1;
+    }
+    let k = exp + shift;
+    let d = BigUint::from(1u32) << shift;
+
+    // ln(m) = 2 atanh(z) with z = (m - 1) / (m + 1), |z| < 0.2
+    let num = BigInt::from(sig.clone()) - BigInt::from(d.clone());
+    let den = BigInt::from(sig + d);
+    let z = div_floor(&(num << w), &den);
+    let z2 = mul_fixed(&z, &z, w);
+    let mut sum = z.clone();
+    let mut power = z;
+    let mut i = 0u64;
+    loop {
+        i += 1;
+        power = mul_fixed(&power, &z2, w);
+        if power.sign() == Sign::NoSign {
+            break;
+        }
+        sum += &power / (2 * i + 1);
+    }
+    (k, sum << 1, 8 * i + 8)
+}
+
+fn log_fixed(sig: &BigUint, exp: i64, w: usize) -> Interval {
+    let (k, log_m, err) = log_parts(sig, exp, w);
+    let guard = 64 - k.unsigned_abs().leading_zeros() as i64 + 4;
+    let fine = ln2(w + guard as usize);
+    let sum = shift_floor(&((log_m << guard) + fine * k), -guard);
+    Interval::around(sum, err + 2, -(w as i64))
+}
+
+fn log2_fixed(sig: &BigUint, exp: i64, w: usize) -> Interval {
+    let (k, log_m, err) = log_parts(sig, exp, w);
+    let log_m = Interval::around(log_m, err, -(w as i64));
+    let ln2 = Interval::around(ln2(w), 2, -(w as i64));
+    let q = Interval::div(&log_m, &ln2, w);
+    let k = BigInt::from(k) << w;
+    Interval {
+        lo: q.lo + &k,
+        hi: q.hi + k,
+        exp: q.exp,
+    }
+}
+
+// sin(x) and cos(x) for x = (-1)^sign * sig * 2^exp
+fn sin_cos_fixed(sign: bool, sig: &BigUint, exp: i64, w: usize) -> (Interval, Interval) {
+    // x = k pi/2 + r with |r| <= pi/4, pi needs the integer bits of x as well
+    let int_bits = (exp + sig.bits() as i64).max(0);
+    let guard = int_bits + 8;
+    let prec = w + guard as usize;
+    let half_pi = pi(prec) >> 1;
+    let x = fixed(sign, sig, exp, prec);
+    let k = div_floor(&((&x << 1) + &half_pi), &(&half_pi << 1));
+    let r = shift_floor(&(x - &k * half_pi), -guard);
+    // r is within 2 units
+
+    let w_shift = -(w as i64);
+    let r2 = mul_fixed(&r, &r, w);
+    let mut sin = r.clone();
+    let mut term = r;
+    let mut n = 0u64;
+    loop {
+        n += 1;
+        term = -mul_fixed(&term, &r2, w) / ((2 * n) * (2 * n + 1));
+        if term.sign() == Sign::NoSign {
+            break;
+        }
+        sin += &term;
+    }
+    let sin_err = 4 * n + 8;
+    let mut term = BigInt::from(1) << w;
+    let mut cos = term.clone();
+    let mut n = 0u64;
+    loop {
+        n += 1;
+        term = -mul_fixed(&term, &r2, w) / ((2 * n - 1) * (2 * n));
+        if term.sign() == Sign::NoSign {
+            break;
+        }
+        cos += &term;
+    }
+    let cos_err = 4 * n + 8;
+    let sin = Interval::around(sin, sin_err, w_shift);
+    let cos = Interval::around(cos, cos_err, w_shift);
+
+    let quadrant = ((k % 4u32) + 4u32) % 4u32;
+    let quadrant: u32 = quadrant.try_into().unwrap();
+    match quadrant {
+        0 => (sin, cos),
+        1 => (cos, sin.neg()),
+        2 => (sin.neg(), cos.neg()),
+        _ => (cos.neg(), sin),
+    }
+}
+
+// atan(x) for x = (-1)^sign * sig * 2^exp
+fn atan_fixed(sign: bool, sig: &BigUint, exp: i64, w: usize) -> Interval {
+    let w_shift = -(w as i64);
+    let one = BigInt::from(1) << w;
+    // atan(x) = pi/2 - atan(1/x) for |x| > 1
+    let inverse = exp + sig.bits() as i64 > 1;
+    let y = if inverse {
+        div_floor(
+            &(BigInt::from(1) << (2 * w)),
+            &fixed(false, sig, exp, w).max(one.clone()),
+        )
+    } else {
+        fixed(false, sig, exp, w)
+    };
+    // y is within 2 units, and 1/x is exact enough once w covers x
+
+    // atan(y) = 2 atan(y / (1 + sqrt(1 + y^2))), three times gives |y| < 0.1
+    let mut y = y;
+    for _ in 0..3 {
+        let y2 = mul_fixed(&y, &y, w);
+        let root = BigInt::from(((y2 + &one).magnitude() << w).sqrt());
+        y = div_floor(&(y << w), &(&one + root));
+    }
+    let y2 = mul_fixed(&y, &y, w);
+    let mut sum = y.clone();
+    let mut power = y;
+    let mut i = 0u64;
+    loop {
+        i += 1;
+        power = -mul_fixed(&power, &y2, w);
+        if power.sign() == Sign::NoSign {
+            break;
+        }
+        sum += &power / (2 * i + 1);
+    }
+    let mut err = 8 * (4 * i + 8) + 16;
+    let mut res: BigInt = sum << 3;
+    if inverse {
+        res = (pi(w) >> 1) - res;
+        err += 2;
+    }
+    if sign {
+        res = -res;
+    }
+    Interval::around(res, err, w_shift)
+}
+
+// round an end of the interval, pushed outward by half a unit
+// so that it is inexact like the exact result
+fn round_end<T: FloatType>(
+    m: &BigInt,
+    up: bool,
+    exp: i64,
+    env: &FpEnv,
+) -> (BigUint, ExceptionFlags) {
+    let end: BigInt = (m << 1) + if up { 1 } else { -1 };
+    round_pack::<T>(
+        end.sign() == Sign::Minus,
+        exp - 1,
+        end.magnitude(),
+        env.rounding,
+        env.tininess,
+    )
+}
+
+// Ziv's strategy from w fractional bits
+fn ziv<T: FloatType>(
+    w: usize,
+    env: &FpEnv,
+    eval: impl Fn(usize) -> Interval,
+) -> (T, ExceptionFlags) {
+    let mut w = w;
+    loop {
+        let interval = eval(w);
+        let lo = round_end::<T>(&interval.lo, false, interval.exp, env);
+        let hi = round_end::<T>(&interval.hi, true, interval.exp, env);
+        if lo == hi {
+            return (T::from_biguint(&lo.0), lo.1);
+        }
+        w *= 2;
+    }
+}
+
+// starting precision: the significand, guard bits
+// and the leading zeros of a small result
+fn start<T: FloatType>(small: i64) -> usize {
+    T::SIG + 32 + small.max(0) as usize
+}
+
+// bits of +inf or -inf
+fn inf<T: FloatType>(sign: bool) -> T {
+    let sign_bit = BigUint::from(sign as u32) << (T::WIDTH - 1);
+    T::from_biguint(&(sign_bit | (T::max_exp() << (T::SIG - 1))))
+}
+
+fn zero<T: FloatType>(sign: bool) -> T {
+    T::from_biguint(&(BigUint::from(sign as u32) << (T::WIDTH - 1)))
+}
+
+fn one<T: FloatType>() -> T {
+    let (bits, _) = round_pack::<T>(
+        false,
+        0,
+        &BigUint::from(1u32),
+        Default::default(),
+        Default::default(),
+    );
+    T::from_biguint(&bits)
+}
+
+// f64 estimate of (-1)^sign * sig * 2^exp, inf or 0 outside of the f64 range
+fn estimate(sign: bool, sig: &BigUint, exp: i64) -> f64 {
+    let bits = sig.bits() as i64;
+    let top = if bits > 53 {
+        sig >> (bits - 53) as usize
+    } else {
+        sig.clone()
+    };
+    let top = top.iter_u64_digits().next().unwrap_or(0) as f64;
+    let exp = exp + (bits - 53).max(0);
+    let res = top * 2f64.powi(exp.clamp(-2000, 2000) as i32);
+    if sign {
+        -res
+    } else {
+        res
+    }
+}
+
+// exponent range of T: (emin, emax)
+fn exp_range<T: FloatType>() -> (i64, i64) {
+    let bias = (1i64 << (T::EXP - 1)) - 1;
+    (1 - bias, bias)
+}
+
+// (-1)^sign * e^t rounded to T with log2_est estimating t / ln(2),
+// results far outside of the exponent range overflow or underflow directly
+fn exp_or_bound<T: FloatType>(
+    sign: bool,
+    log2_est: f64,
+    env: &FpEnv,
+    eval: impl Fn(usize) -> Interval,
+) -> (T, ExceptionFlags) {
+    let (emin, emax) = exp_range::<T>();
+    if log2_est > (emax + 2) as f64 {
+        let (bits, flags) = round_pack::<T>(
+            sign,
+            emax + 2,
+            &BigUint::from(1u32),
+            env.rounding,
+            env.tininess,
+        );
+        return (T::from_biguint(&bits), flags);
+    }
+    if log2_est < (emin - T::SIG as i64 - 3) as f64 {
+        // below half of the smallest subnormal number
+        let exp = emin - T::SIG as i64 - 3;
+        let (bits, flags) =
+            round_pack::<T>(sign, exp, &BigUint::from(3u32), env.rounding, env.tininess);
+        return (T::from_biguint(&bits), flags);
+    }
+    ziv(start::<T>(0), env, |w| {
+        let res = eval(w);
+        if sign {
+            res.neg()
+        } else {
+            res
+        }
+    })
+}
+
+// nan operands: propagated, and invalid if signaling
+fn nan_result<T: FloatType>(operands: &[T], env: &FpEnv) -> Option<(T, ExceptionFlags)> {
+    let res = propagate_nan(env.nan_policy, operands)?;
+    let invalid = operands.iter().any(|&a| {
+        let (_, exp, man) = extract::<T>(&a.to_biguint());
+        exp == T::max_exp() && man.bits() != 0 && !man.bit(T::SIG as u64 - 2)
+    });
+    let flags = ExceptionFlags {
+        invalid,
+        ..Default::default()
+    };
+    Some((res, flags))
+}
+
+fn invalid<T: FloatType>(env: &FpEnv) -> (T, ExceptionFlags) {
+    let flags = ExceptionFlags {
+        invalid: true,
+        ..Default::default()
+    };
+    (invalid_nan(env.nan_policy), flags)
+}
+
+// (is inf, is zero) of a non-nan value
+fn special<T: FloatType>(a: T) -> (bool, bool) {
+    let (_, exp, man) = extract::<T>(&a.to_biguint());
+    (exp == T::max_exp(), exp.bits() == 0 && man.bits() == 0)
+}
+
+pub fn cr_exp<T: FloatType>(x: T, env: &FpEnv) -> (T, ExceptionFlags) {
+    if let Some(res) = nan_result(&[x], env) {
+        return res;
+    }
+    let (sign, exp, sig) = decode::<T>(&x.to_biguint());
+    match special(x) {
+        (true, _) if sign => return (zero(false), Default::default()),
+        (true, _) => return (x, Default::default()),
+        // exp(0) = 1 is the only exact case
+        (_, true) => return (one(), Default::default()),
+        _ => {}
+    }
+    let log2_est = estimate(sign, &sig, exp) * std::f64::consts::LOG2_E;
+    exp_or_bound(false, log2_est, env, |w| {
+        let lo = fixed(sign, &sig, exp, w);
+        let hi = -fixed(!sign, &sig, exp, w);
+        if lo == hi {
+            exp_fixed(&lo, w)
+        } else {
+            Interval::hull(exp_fixed(&lo, w), exp_fixed(&hi, w))
+        }
+    })
+}
+
+// shared by log and log2: nan, negative, zero, inf and one
+fn log_special<T: FloatType>(x: T, env: &FpEnv) -> Option<(T, ExceptionFlags)> {
+    if let Some(res) = nan_result(&[x], env) {
+        return Some(res);
+    }
+    let (sign, _, _) = decode::<T>(&x.to_biguint());
+    match special(x) {
+        (_, true) => {
+            let flags = ExceptionFlags {
+                div_by_zero: true,
+                ..Default::default()
+            };
+            Some((inf(true), flags))
+        }
+        _ if sign => Some(invalid(env)),
+        (true, _) => Some((x, Default::default())),
+        _ => None,
+    }
+}
+
+// leading zeros of x - 1, log(x) is close to it when x is near 1
+fn near_one(sig: &BigUint, exp: i64) -> i64 {
+    if exp >= 0 {
+        return 0;
+    }
+    let one = BigInt::from(1) << -exp;
+    let diff = BigInt::from(sig.clone()) - one;
+    -(exp + diff.bits() as i64)
+}
+
+pub fn cr_log<T: FloatType>(x: T, env: &FpEnv) -> (T, ExceptionFlags) {
+    if let Some(res) = log_special(x, env) {
+        return res;
+    }
+    let (_, exp, sig) = decode::<T>(&x.to_biguint());
+    if sig.count_ones() == 1 && exp + sig.bits() as i64 == 1 {
+        // log(1) = 0 is the only exact case
+        return (zero(false), Default::default());
+    }
+    // the result is near 0 for x near 1
+    ziv(start::<T>(near_one(&sig, exp)), env, |w| {
+        log_fixed(&sig, exp, w)
+    })
+}
+
+pub fn cr_log2<T: FloatType>(x: T, env: &FpEnv) -> (T, ExceptionFlags) {
+    if let Some(res) = log_special(x, env) {
+        return res;
+    }
+    let (_, exp, sig) = decode::<T>(&x.to_biguint());
+    if sig.count_ones() == 1 {
+        // powers of two are the exact cases
+        let k = exp + sig.bits() as i64 - 1;
+        let (bits, flags) = round_pack::<T>(
+            k < 0,
+            0,
+            &BigUint::from(k.unsigned_abs()),
+            env.rounding,
+            env.tininess,
+        );
+        return (T::from_biguint(&bits), flags);
+    }
+    ziv(start::<T>(near_one(&sig, exp)), env, |w| {
+        log2_fixed(&sig, exp, w)
+    })
+}
+
+// shared by sin, cos and tan: nan, inf and zero
+fn trig_special<T: FloatType>(x: T, env: &FpEnv, zero_res: T) -> Option<(T, ExceptionFlags)> {
+    if let Some(res) = nan_result(&[x], env) {
+        return Some(res);
+    }
+    match special(x) {
+        (true, _) => Some(invalid(env)),
+        (_, true) => Some((zero_res, Default::default())),
+        _ => None,
+    }
+}
+
+// leading zeros of x, sin(x), tan(x) and atan(x) are close to x when it is small
+fn small_x(sig: &BigUint, exp: i64) -> i64 {
+    -(exp + sig.bits() as i64)
+}
+
+pub fn cr_sin<T: FloatType>(x: T, env: &FpEnv) -> (T, ExceptionFlags) {
+    if let Some(res) = trig_special(x, env, x) {
+        return res;
+    }
+    let (sign, exp, sig) = decode::<T>(&x.to_biguint());
+    ziv(start::<T>(small_x(&sig, exp)), env, |w| {
+        sin_cos_fixed(sign, &sig, exp, w).0
+    })
+}
+
+pub fn cr_cos<T: FloatType>(x: T, env: &FpEnv) -> (T, ExceptionFlags) {
+    if let Some(res) = trig_special(x, env, one()) {
+        return res;
+    }
+    let (sign, exp, sig) = decode::<T>(&x.to_biguint());
+    ziv(start::<T>(0), env, |w| sin_cos_fixed(sign, &sig, exp, w).1)
+}
+
+pub fn cr_tan<T: FloatType>(x: T, env: &FpEnv) -> (T, ExceptionFlags) {
+    if let Some(res) = trig_special(x, env, x) {
+        return res;
+    }
+    let (sign, exp, sig) = decode::<T>(&x.to_biguint());
+    ziv(start::<T>(small_x(&sig, exp)), env, |w| {
+        let (sin, cos) = sin_cos_fixed(sign, &sig, exp, w);
+        Interval::div(&sin, &cos, w)
+    })
+}
+
+pub fn cr_atan<T: FloatType>(x: T, env: &FpEnv) -> (T, ExceptionFlags) {
+    if let Some(res) = nan_result(&[x], env) {
+        return res;
+    }
+    let (sign, exp, sig) = decode::<T>(&x.to_biguint());
+    match special(x) {
+        // atan(inf) = pi/2
+        (true, _) => {
+            return ziv(start::<T>(0), env, |w| {
+                let half_pi = Interval::around(pi(w) >> 1, 2, -(w as i64));
+                if sign {
+                    half_pi.neg()
+                } else {
+                    half_pi
+                }
+            })
+        }
+        (_, true) => return (x, Default::default()),
+        _ => {}
+    }
+    ziv(start::<T>(small_x(&sig, exp)), env, |w| {
+        atan_fixed(sign, &sig, exp, w)
+    })
+}
+
+// x^y as sig * 2^exp when it is a dyadic number of at most max_bits bits,
+// x = sig_x * 2^exp_x > 0 and y = (-1)^sign_y * sig_y * 2^exp_y
+fn pow_exact(
+    sig_x: &BigUint,
+    exp_x: i64,
+    sign_y: bool,
+    sig_y: &BigUint,
+    exp_y: i64,
+    max_bits: u64,
+) -> Option<(BigUint, i64)> {
+    // odd parts
+    let zeros = sig_x.trailing_zeros().unwrap_or(0);
+    let (mut odd, mut exp) = (sig_x >> zeros, exp_x + zeros as i64);
+    let zeros_y = sig_y.trailing_zeros().unwrap_or(0);
+    let (m, mut k) = (sig_y >> zeros_y, -(exp_y + zeros_y as i64));
+    // y = m / 2^k, take k square roots of x
+    while k > 0 {
+        let root = odd.sqrt();
+        if &root * &root != odd || exp % 2 != 0 {
+            return None;
+        }
+        odd = root;
+        exp /= 2;
+        k -= 1;
+    }
+    // y is now the integer m * 2^-k
+    if -k >= 63 {
+        return None;
+    }
+    let m: u64 = m.try_into().ok()?;
+    let n = m.checked_mul(1 << -k)?;
+    if sign_y && odd.bits() > 1 {
+        // 1 / odd is not dyadic
+        return None;
+    }
+    // powers of two are exact, otherwise odd^n has at least n (bits - 1) + 1 bits
+    // and is neither exact nor a midpoint beyond max_bits
+    if odd.bits() > 1 && odd.bits() * n > max_bits {
+        return None;
+    }
+    let n32: u32 = n.try_into().ok()?;
+    let exp = exp.checked_mul(n as i64)?;
+    let exp = if sign_y { -exp } else { exp };
+    Some((odd.pow(n32), exp))
+}
+
+pub fn cr_pow<T: FloatType>(x: T, y: T, env: &FpEnv) -> (T, ExceptionFlags) {
+    let (sign_x, exp_x, sig_x) = decode::<T>(&x.to_biguint());
+    let (sign_y, exp_y, sig_y) = decode::<T>(&y.to_biguint());
+    let (_, zero_x) = special(x);
+    let (inf_y, zero_y) = special(y);
+    let (nan_x, nan_y) = (decode_nan(x), decode_nan(y));
+    let inf_y = inf_y && nan_y.is_none();
+    let snan = [nan_x, nan_y]
+        .into_iter()
+        .any(|nan| matches!(nan, Some((_, false, _))));
+    let x_is_one = !sign_x && sig_x.count_ones() == 1 && exp_x + sig_x.bits() as i64 == 1;
+    if !snan && (zero_y || x_is_one) {
+        // pow(x, 0) and pow(1, y) are 1 even for a quiet nan
+        return (one(), Default::default());
+    }
+    if let Some(res) = nan_result(&[x, y], env) {
+        return res;
+    }
+
+    if inf_y {
+        // |x| = 1, |x| < 1 or |x| > 1
+        let magnitude = exp_x + sig_x.bits() as i64 - 1;
+        let exactly_one = sig_x.count_ones() == 1 && magnitude == 0;
+        return if exactly_one {
+            (one(), Default::default())
+        } else if (zero_x || magnitude < 0) != sign_y {
+            (zero(false), Default::default())
+        } else {
+            (inf(false), Default::default())
+        };
+    }
+    pow_finite(x, sign_y, exp_y, &sig_y, env)
+}
+
+// x^n for an exact integer n, pown of IEEE 754
+// unlike cr_pow(x, n as T), n is not rounded to the precision of T
+pub fn cr_pown<T: FloatType>(x: T, n: i64, env: &FpEnv) -> (T, ExceptionFlags) {
+    let snan = matches!(decode_nan(x), Some((_, false, _)));
+    if !snan && n == 0 {
+        // pown(x, 0) is 1 even for a quiet nan
+        return (one(), Default::default());
+    }
+    if let Some(res) = nan_result(&[x], env) {
+        return res;
+    }
+    pow_finite(x, n < 0, 0, &BigUint::from(n.unsigned_abs()), env)
+}
+
+// x^y for a nonzero finite y = (-1)^sign_y * sig_y * 2^exp_y and a non nan x
+fn pow_finite<T: FloatType>(
+    x: T,
+    sign_y: bool,
+    exp_y: i64,
+    sig_y: &BigUint,
+    env: &FpEnv,
+) -> (T, ExceptionFlags) {
+    let exact = |sign: bool, sig: &BigUint, exp: i64| {
+        let (bits, flags) = round_pack::<T>(sign, exp, sig, env.rounding, env.tininess);
+        (T::from_biguint(&bits), flags)
+    };
+    let (sign_x, exp_x, sig_x) = decode::<T>(&x.to_biguint());
+    let (inf_x, zero_x) = special(x);
+
+    // integer and odd integer y
+    let zeros_y = sig_y.trailing_zeros().unwrap_or(0) as i64;
+    let integer = exp_y + zeros_y >= 0;
+    let odd = exp_y + zeros_y == 0;
+
+    if zero_x {
+        let sign = sign_x && odd;
+        return if sign_y {
+            let flags = ExceptionFlags {
+                div_by_zero: true,
+                ..Default::default()
+            };
+            (inf(sign), flags)
+        } else {
+            (zero(sign), Default::default())
+        };
+    }
+    if inf_x {
+        let sign = sign_x && odd;
+        return if sign_y {
+            (zero(sign), Default::default())
+        } else {
+            (inf(sign), Default::default())
+        };
+    }
+    if sign_x && !integer {
+        return invalid(env);
+    }
+    let sign = sign_x && odd;
+
+    // log2 |x^y|
+    let log2_est = estimate(sign_y, sig_y, exp_y) * estimate(false, &sig_x, exp_x).log2();
+    let (emin, emax) = exp_range::<T>();
+    let in_range = log2_est.abs() <= (emax - emin + 2 * T::SIG as i64) as f64;
+    let max_bits = 2 * T::SIG as u64 + 2;
+    if in_range {
+        if let Some((sig, exp)) = pow_exact(&sig_x, exp_x, sign_y, sig_y, exp_y, max_bits) {
+            return exact(sign, &sig, exp);
+        }
+    }
+
+    // e^(y ln|x|) with y ln|x| in fixed point, y scales the error of ln|x|
+    let y_bits = (exp_y + sig_y.bits() as i64).max(0) as usize;
+    exp_or_bound(sign, log2_est, env, |w| {
+        let wide = w + y_bits + 8;
+        let log = log_fixed(&sig_x, exp_x, wide);
+        let y_fixed =
+            BigInt::from_biguint(if sign_y { Sign::Minus } else { Sign::Plus }, sig_y.clone());
+        // y * log to w fractional bits
+        let shift = exp_y + log.exp + w as i64;
+        let (a, b) = (&y_fixed * &log.lo, &y_fixed * &log.hi);
+        let (lo, hi) = if sign_y { (b, a) } else { (a, b) };
+        let lo = shift_floor(&lo, shift);
+        let hi = -shift_floor(&-hi, shift);
+        Interval::hull(exp_fixed(&lo, w), exp_fixed(&hi, w))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        cr_atan, cr_cos, cr_exp, cr_log, cr_log2, cr_pow, cr_pown, cr_sin, cr_tan, ExceptionFlags,
+        FpEnv, RoundingMode,
+    };
+    use half::f16;
+
+    // f64 std result rounded to f32, None when it is too close to a rounding boundary
+    fn reference(expected: f64) -> Option<f32> {
+        let res = expected as f32;
+        let lo = (expected * (1.0 - 1e-13)) as f32;
+        let hi = (expected * (1.0 + 1e-13)) as f32;
+        (lo == res && hi == res && expected.is_finite()).then_some(res)
+    }
+
+    #[test]
+    fn test() {
+        let env = FpEnv::default();
+        type Unary = fn(f32, &FpEnv) -> (f32, ExceptionFlags);
+        type Native = fn(f64) -> f64;
+        let functions: [(Unary, Native); 7] = [
+            (cr_exp, f64::exp),
+            (cr_log, f64::ln),
+            (cr_log2, f64::log2),
+            (cr_sin, f64::sin),
+            (cr_cos, f64::cos),
+            (cr_tan, f64::tan),
+            (cr_atan, f64::atan),
+        ];
+        let mut state = 0x2545f4914f6cdd1du64;
+        for _ in 0..300 {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            // exponents around 2^-20 to 2^12, positive and negative
+            let a = f32::from_bits(
+                (state as u32 & 0x807fffff) | ((107 + (state >> 40) as u32 % 32) << 23),
+            );
+            let b = f32::from_bits(state as u32);
+            for x in [a, b, a.abs()] {
+                if x.is_nan() {
+                    continue;
+                }
+                for (cr, native) in functions {
+                    let (res, _) = cr(x, &env);
+                    let native = native(x as f64);
+                    if native.is_nan() {
+                        assert!(res.is_nan(), "{}", x);
+                    } else if let Some(expected) = reference(native) {
+                        assert_eq!(res.to_bits(), expected.to_bits(), "{} {}", x, native);
+                    }
+                }
+                let y = f32::from_bits(0x3f000000 | (state >> 41) as u32 & 0x807fffff);
+                let native = (x as f64).powf(y as f64);
+                let (res, _) = cr_pow(x, y, &env);
+                if native.is_nan() {
+                    assert!(res.is_nan(), "{} {}", x, y);
+                } else if let Some(expected) = reference(native) {
+                    assert_eq!(res.to_bits(), expected.to_bits(), "{} {}", x, y);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_f64() {
+        let env = FpEnv::default();
+        for (res, expected) in [
+            (cr_exp(1.0f64, &env), 0x4005bf0a8b145769u64),
+            (cr_log(2.0, &env), 0x3fe62e42fefa39ef),
+            (cr_sin(1.0, &env), 0x3feaed548f090cee),
+            (cr_cos(1.0, &env), 0x3fe14a280fb5068c),
+            (cr_tan(1.0, &env), 0x3ff8eb245cbee3a6),
+            (cr_atan(1.0, &env), 0x3fe921fb54442d18),
+            (cr_log2(10.0, &env), 0x400a934f0979a371),
+            (cr_pow(2.0, 0.5, &env), 0x3ff6a09e667f3bcd),
+            (cr_atan(f64::INFINITY, &env), 0x3ff921fb54442d18),
+        ] {
+            assert_eq!(res.0.to_bits(), expected);
+            assert_eq!(res.1.to_bits(), 0b00001);
+        }
+        // far from zero the reduction needs many bits of pi
+        let (res, _) = cr_sin(1e22f64, &env);
+        assert_eq!(res.to_bits(), (-0.8522008497671888f64).to_bits());
+    }
+
+    #[test]
+    fn test_rounding() {
+        let env = |rounding| FpEnv {
+            rounding,
+            ..Default::default()
+        };
+        let down = env(RoundingMode::TowardNegative);
+        let up = env(RoundingMode::TowardPositive);
+        let zero = env(RoundingMode::TowardZero);
+        // f16 inputs bracket the exact result
+        for num in (0..=u16::MAX).step_by(5) {
+            let x = f16::from_bits(num);
+            if !x.is_finite() {
+                continue;
+            }
+            let x64 = x.to_f64();
+            for (cr, native) in [
+                (cr_exp::<f16> as fn(_, _) -> _, f64::exp as fn(f64) -> f64),
+                (cr_log, f64::ln),
+                (cr_sin, f64::sin),
+                (cr_atan, f64::atan),
+            ] {
+                let (lo, lo_flags) = cr(x, &down);
+                let (hi, _) = cr(x, &up);
+                let native = native(x64);
+                if native.is_nan() {
+                    assert!(lo.is_nan() && hi.is_nan());
+                    continue;
+                }
+                if !native.is_normal() && lo_flags.inexact {
+                    // e^x beyond the f64 range
+                    continue;
+                }
+                if lo_flags.inexact {
+                    assert!(lo.to_f64() < native && native < hi.to_f64(), "{}", x);
+                    // adjacent, the negative one has the larger encoding
+                    let (small, large) = if native < 0.0 { (hi, lo) } else { (lo, hi) };
+                    assert_eq!(small.to_bits() + 1, large.to_bits(), "{}", x);
+                } else {
+                    assert_eq!(lo, hi);
+                    assert_eq!(lo.to_f64(), native);
+                }
+                let (res, _) = cr(x, &zero);
+                let toward_zero = if native < 0.0 { hi } else { lo };
+                assert_eq!(res.to_bits(), toward_zero.to_bits(), "{}", x);
+            }
+        }
+    }
+
+    #[test]
+    fn test_special() {
+        let env = FpEnv::default();
+        let inf = f32::INFINITY;
+        let snan = f32::from_bits(0x7f800001);
+        for (res, expected, flags) in [
+            (cr_exp(0.0f32, &env), 1.0f32, 0b00000),
+            (cr_exp(-inf, &env), 0.0, 0b00000),
+            (cr_exp(inf, &env), inf, 0b00000),
+            (cr_exp(100.0, &env), inf, 0b00101),
+            (cr_exp(-200.0, &env), 0.0, 0b00011),
+            (cr_exp(-103.5, &env), f32::from_bits(1), 0b00011),
+            (cr_log(1.0, &env), 0.0, 0b00000),
+            (cr_log(0.0, &env), -inf, 0b01000),
+            (cr_log(-0.0, &env), -inf, 0b01000),
+            (cr_log(inf, &env), inf, 0b00000),
+            (cr_log2(0.125, &env), -3.0, 0b00000),
+            (cr_log2(1.0, &env), 0.0, 0b00000),
+            (cr_sin(-0.0, &env), -0.0, 0b00000),
+            (cr_cos(-0.0, &env), 1.0, 0b00000),
+            (cr_tan(-0.0, &env), -0.0, 0b00000),
+            (cr_atan(-0.0, &env), -0.0, 0b00000),
+            (cr_sin(f32::from_bits(1), &env), f32::from_bits(1), 0b00011),
+            (cr_pow(f32::NAN, 0.0, &env), 1.0, 0b00000),
+            (cr_pow(1.0, f32::NAN, &env), 1.0, 0b00000),
+            (cr_pow(-1.0, inf, &env), 1.0, 0b00000),
+            (cr_pow(0.5, -inf, &env), inf, 0b00000),
+            (cr_pow(0.0, -inf, &env), inf, 0b00000),
+            (cr_pow(2.0, inf, &env), inf, 0b00000),
+            (cr_pow(-0.0, -3.0, &env), -inf, 0b01000),
+            (cr_pow(-0.0, 3.0, &env), -0.0, 0b00000),
+            (cr_pow(-0.0, 2.0, &env), 0.0, 0b00000),
+            (cr_pow(-inf, 3.0, &env), -inf, 0b00000),
+            (cr_pow(-inf, -2.0, &env), 0.0, 0b00000),
+            (cr_pow(-2.0, 3.0, &env), -8.0, 0b00000),
+            (cr_pow(-3.0, -1.0, &env), -1.0 / 3.0, 0b00001),
+            (cr_pow(2.0, -149.0, &env), f32::from_bits(1), 0b00000),
+            (cr_pow(2.0, 128.0, &env), inf, 0b00101),
+            (cr_pow(2.25, 1.5, &env), 3.375, 0b00000),
+            (cr_pow(4.0, -0.5, &env), 0.5, 0b00000),
+            (cr_pow(3.0, 15.0, &env), 14348907.0, 0b00000),
+            (cr_pow(3.0, 16.0, &env), 43046721.0, 0b00001),
+            (cr_pown(f32::NAN, 0, &env), 1.0, 0b00000),
+            (cr_pown(-0.0, -3, &env), -inf, 0b01000),
+            (cr_pown(-inf, 3, &env), -inf, 0b00000),
+            (cr_pown(-1.0, (1 << 24) + 1, &env), -1.0, 0b00000),
+            (cr_pown(2.0, -149, &env), f32::from_bits(1), 0b00000),
+            (cr_pown(3.0, 16, &env), 43046721.0, 0b00001),
+            (cr_pown(0.5, i64::MIN, &env), inf, 0b00101),
+        ] {
+            assert_eq!(res.0.to_bits(), expected.to_bits(), "{:?}", res);
+            assert_eq!(res.1.to_bits(), flags, "{:?}", res);
+        }
+
+        // invalid operations and nans
+        for (res, flags) in [
+            (cr_log(-1.0f32, &env), 0b10000),
+            (cr_log2(-inf, &env), 0b10000),
+            (cr_sin(inf, &env), 0b10000),
+            (cr_cos(-inf, &env), 0b10000),
+            (cr_tan(inf, &env), 0b10000),
+            (cr_pow(-2.0, 0.5, &env), 0b10000),
+            (cr_exp(snan, &env), 0b10000),
+            (cr_pow(snan, 0.0, &env), 0b10000),
+            (cr_pown(snan, 0, &env), 0b10000),
+            (cr_atan(f32::NAN, &env), 0b00000),
+        ] {
+            assert!(res.0.is_nan());
+            assert_eq!(res.1.to_bits(), flags);
+        }
+    }
+}
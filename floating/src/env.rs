@@ -0,0 +1,346 @@
+use num_bigint::{BigUint, ToBigUint};
+use std::fmt::Display;
+
+use crate::{
+    extract, round_pack, ExceptionFlags, FloatType, NanPolicy, RoundingMode, Saturation, Tininess,
+};
+
+// flags raised when flushing subnormal numbers
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FlushFlags {
+    // x86 MXCSR.DAZ/FTZ: flushed inputs raise nothing,
+    // flushed results raise underflow and inexact
+    #[default]
+    X86,
+    // ARM FPCR.FZ: flushed inputs raise input_denormal,
+    // flushed results raise underflow but not inexact
+    Arm,
+}
+
+// settings shared by the soft-float operations
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FpEnv {
+    // dynamic rounding mode, operations with a fixed direction take their own
+    pub rounding: RoundingMode,
+    pub tininess: Tininess,
+    pub nan_policy: NanPolicy,
+    // denormals are zero: subnormal inputs are read as zeros of the same sign
+    pub daz: bool,
+    // flush to zero: tiny results are replaced by zeros of the same sign
+    pub ftz: bool,
+    pub flush_flags: FlushFlags,
+    // float to integer conversion of nan and out of range inputs
+    pub saturation: Saturation,
+}
+
+// behavior of common targets with their default control registers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Profile {
+    X86Sse,
+    ArmAarch64,
+    RiscV,
+    // nvcc defaults without --use_fast_math, no exception flags in hardware
+    Cuda,
+    // the IEEE 754 defaults, with x86 nan propagation
+    IeeeStrict,
+}
+
+impl Profile {
+    pub const ALL: [Profile; 5] = [
+        Profile::X86Sse,
+        Profile::ArmAarch64,
+        Profile::RiscV,
+        Profile::Cuda,
+        Profile::IeeeStrict,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Profile::X86Sse => "x86_sse",
+            Profile::ArmAarch64 => "arm_aarch64",
+            Profile::RiscV => "riscv",
+            Profile::Cuda => "cuda",
+            Profile::IeeeStrict => "ieee_strict",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Profile> {
+        Profile::ALL
+            .into_iter()
+            .find(|profile| profile.name() == name)
+    }
+}
+
+impl Display for Profile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FpEnv {
+    pub fn profile(profile: Profile) -> FpEnv {
+        let (tininess, nan_policy, flush_flags, saturation) = match profile {
+            Profile::X86Sse => (
+                Tininess::AfterRounding,
+                NanPolicy::X86,
+                FlushFlags::X86,
+                Saturation::X86,
+            ),
+            Profile::ArmAarch64 => (
+                Tininess::BeforeRounding,
+                NanPolicy::Arm,
+                FlushFlags::Arm,
+                Saturation::Arm,
+            ),
+            Profile::RiscV => (
+                Tininess::AfterRounding,
+                NanPolicy::Canonical,
+                FlushFlags::X86,
+                Saturation::RiscV,
+            ),
+            // cvt.rzi saturates and converts nan to 0 like ARM
+            Profile::Cuda => (
+                Tininess::AfterRounding,
+                NanPolicy::Cuda,
+                FlushFlags::X86,
+                Saturation::Arm,
+            ),
+            Profile::IeeeStrict => (
+                Tininess::AfterRounding,
+                NanPolicy::X86,
+                FlushFlags::X86,
+                Saturation::RiscV,
+            ),
+        };
+        // every target starts with round to nearest even and without flushing
+        FpEnv {
+            rounding: RoundingMode::NearestEven,
+            tininess,
+            nan_policy,
+            daz: false,
+            ftz: false,
+            flush_flags,
+            saturation,
+        }
+    }
+}
+
+fn is_subnormal<T: FloatType>(a: T) -> bool {
+    let (_, exp, man) = extract::<T>(&a.to_biguint());
+    exp.bits() == 0 && man.bits() != 0
+}
+
+fn signed_zero<T: FloatType>(a: T) -> T {
+    let sign_bit = 1.to_biguint().unwrap() << (T::WIDTH - 1);
+    T::from_biguint(&(a.to_biguint() & sign_bit))
+}
+
+// apply DAZ to an operand
+pub(crate) fn flush_input<T: FloatType>(a: T, env: &FpEnv, flags: &mut ExceptionFlags) -> T {
+    if !env.daz || !is_subnormal(a) {
+        return a;
+    }
+    if env.flush_flags == FlushFlags::Arm {
+        flags.input_denormal = true;
+    }
+    signed_zero(a)
+}
+
+// apply FTZ to a result, tiny is decided by the caller
+// an exactly representable subnormal result is flushed as well
+pub(crate) fn flush_output<T: FloatType>(
+    res: T,
+    tiny: bool,
+    env: &FpEnv,
+    flags: &mut ExceptionFlags,
+) -> T {
+    if !env.ftz || !tiny {
+        return res;
+    }
+    flags.underflow = true;
+    flags.inexact = env.flush_flags == FlushFlags::X86;
+    signed_zero(res)
+}
+
+// tiny results of a rounding with flags round_flags, for flush_output
+// before rounding: the exact value is below the smallest normal number
+pub(crate) fn is_tiny<T: FloatType>(
+    res: T,
+    tiny_before: bool,
+    tininess: Tininess,
+    round_flags: &ExceptionFlags,
+) -> bool {
+    match tininess {
+        Tininess::BeforeRounding => tiny_before,
+        Tininess::AfterRounding => round_flags.underflow || is_subnormal(res),
+    }
+}
+
+// round (-1)^sign * sig * 2^exp to T in the rounding mode of env, with FTZ
+// sig may carry a sticky lsb, it does not change tininess before rounding
+pub(crate) fn round_env<T: FloatType>(
+    sign: bool,
+    exp: i64,
+    sig: &BigUint,
+    env: &FpEnv,
+) -> (T, ExceptionFlags) {
+    let (bits, mut flags) = round_pack::<T>(sign, exp, sig, env.rounding, env.tininess);
+    // below the smallest normal number of T
+    let emin = 2 - (1i64 << (T::EXP - 1));
+    let tiny_before = sig.bits() != 0 && exp + (sig.bits() as i64) - 1 < emin;
+    let res = T::from_biguint(&bits);
+    let tiny = is_tiny(res, tiny_before, env.tininess, &flags);
+    let res = flush_output(res, tiny, env, &mut flags);
+    (res, flags)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        float_to_int, minimum, round_to_integral_exact, softfloat_add, softfloat_add_trace,
+        softfloat_convert, softfloat_sub, softfloat_sub_trace, ExceptionFlags, FlushFlags, FpEnv,
+        Profile, RoundingMode, Tininess,
+    };
+    use num_bigint::BigInt;
+
+    #[test]
+    fn test_add() {
+        let tiny = f32::from_bits(1);
+        let min = f32::MIN_POSITIVE;
+        for flush_flags in [FlushFlags::X86, FlushFlags::Arm] {
+            let arm = flush_flags == FlushFlags::Arm;
+            let daz = FpEnv {
+                daz: true,
+                flush_flags,
+                ..Default::default()
+            };
+            let ftz = FpEnv {
+                ftz: true,
+                flush_flags,
+                ..Default::default()
+            };
+
+            // subnormal inputs are read as zeros
+            let (res, trace) = softfloat_add_trace(1.0f32, tiny, &daz);
+            assert_eq!(res, 1.0);
+            let expected = ExceptionFlags {
+                input_denormal: arm,
+                ..Default::default()
+            };
+            assert_eq!(trace.flags, expected);
+            let (res, trace) = softfloat_sub_trace(-tiny, tiny, &daz);
+            assert_eq!(res.to_bits(), (-0.0f32).to_bits());
+            assert_eq!(trace.flags, expected);
+            let (res, _) = softfloat_add_trace(-tiny, tiny, &daz);
+            assert_eq!(res.to_bits(), 0);
+            // FTZ alone keeps subnormal inputs
+            let (res, trace) = softfloat_add_trace(1.0f32, tiny, &ftz);
+            assert_eq!(res, 1.0);
+            assert!(trace.flags.inexact);
+
+            // subnormal results are flushed
+            let expected = ExceptionFlags {
+                underflow: true,
+                inexact: !arm,
+                ..Default::default()
+            };
+            let (res, trace) = softfloat_sub_trace(min, tiny, &ftz);
+            assert_eq!(res.to_bits(), 0);
+            assert_eq!(trace.flags, expected);
+            let (res, trace) = softfloat_add_trace(-min, tiny, &ftz);
+            assert_eq!(res.to_bits(), (-0.0f32).to_bits());
+            assert_eq!(trace.flags, expected);
+            let (res, trace) = softfloat_add_trace(tiny, tiny, &ftz);
+            assert_eq!(res.to_bits(), 0);
+            assert_eq!(trace.flags, expected);
+            // the smallest normal number and exact zeros are kept
+            let (res, trace) = softfloat_add_trace(min, tiny, &ftz);
+            assert_eq!(res, min + tiny);
+            assert_eq!(trace.flags, ExceptionFlags::default());
+            let (res, trace) = softfloat_sub_trace(min, min, &ftz);
+            assert_eq!(res.to_bits(), 0);
+            assert_eq!(trace.flags, ExceptionFlags::default());
+        }
+    }
+
+    #[test]
+    fn test_other() {
+        let rne = RoundingMode::NearestEven;
+        let daz = FpEnv {
+            daz: true,
+            ..Default::default()
+        };
+        let ftz = FpEnv {
+            ftz: true,
+            ..Default::default()
+        };
+        let tiny = f32::from_bits(1);
+
+        let (res, flags) = softfloat_convert::<f32, f64>(-tiny, &daz);
+        assert_eq!(res.to_bits(), (-0.0f64).to_bits());
+        assert_eq!(flags, ExceptionFlags::default());
+        let (res, flags) = minimum(-tiny, 0.0, &daz);
+        assert_eq!(res.to_bits(), (-0.0f32).to_bits());
+        assert_eq!(flags, ExceptionFlags::default());
+        let (res, flags) = round_to_integral_exact(-tiny, rne, &daz);
+        assert_eq!(res.to_bits(), (-0.0f32).to_bits());
+        assert_eq!(flags, ExceptionFlags::default());
+
+        // exact subnormal result
+        let a = (tiny as f64) * 3.0;
+        let (res, flags) = softfloat_convert::<f64, f32>(a, &ftz);
+        assert_eq!(res.to_bits(), 0);
+        assert_eq!(flags.to_bits(), 0b00011);
+        // rounds up to the smallest normal number, which is only tiny before rounding
+        let a = f32::MIN_POSITIVE as f64 * (1.0 - f64::EPSILON);
+        let (res, flags) = softfloat_convert::<f64, f32>(a, &ftz);
+        assert_eq!(res, f32::MIN_POSITIVE);
+        assert_eq!(flags.to_bits(), 0b00001);
+        let (res, flags) = softfloat_convert::<f64, f32>(
+            a,
+            &FpEnv {
+                tininess: Tininess::BeforeRounding,
+                ..ftz
+            },
+        );
+        assert_eq!(res.to_bits(), 0);
+        assert_eq!(flags.to_bits(), 0b00011);
+    }
+
+    #[test]
+    fn test_profile() {
+        for profile in Profile::ALL {
+            assert_eq!(Profile::from_name(profile.name()), Some(profile));
+            assert_eq!(profile.to_string(), profile.name());
+        }
+        assert_eq!(Profile::from_name("x87"), None);
+
+        let qnan = f32::from_bits(0x7fc00001);
+        let rtz = RoundingMode::TowardZero;
+        // tiny before rounding only
+        let a = f32::MIN_POSITIVE as f64 * (1.0 - f64::EPSILON);
+        for (profile, invalid, propagated, nan_to_int, underflow) in [
+            (
+                Profile::X86Sse,
+                0xffc00000u32,
+                0x7fc00001u32,
+                i32::MIN,
+                false,
+            ),
+            (Profile::ArmAarch64, 0x7fc00000, 0x7fc00001, 0, true),
+            (Profile::RiscV, 0x7fc00000, 0x7fc00000, i32::MAX, false),
+            (Profile::Cuda, 0x7fffffff, 0x7fffffff, 0, false),
+            (Profile::IeeeStrict, 0xffc00000, 0x7fc00001, i32::MAX, false),
+        ] {
+            let env = FpEnv::profile(profile);
+            let res = softfloat_sub(f32::INFINITY, f32::INFINITY, &env).0;
+            assert_eq!(res.to_bits(), invalid, "{}", profile);
+            let res = softfloat_add(1.0, qnan, &env).0;
+            assert_eq!(res.to_bits(), propagated, "{}", profile);
+            let (res, _) = float_to_int(f32::NAN, true, 32, rtz, &env);
+            assert_eq!(res, BigInt::from(nan_to_int), "{}", profile);
+            let (_, flags) = softfloat_convert::<f64, f32>(a, &env);
+            assert_eq!(flags.underflow, underflow, "{}", profile);
+        }
+    }
+}
@@ -0,0 +1,308 @@
+use num_bigint::{BigUint, ToBigUint};
+use std::cmp::max;
+
+use crate::{extract, FloatType};
+
+// right shift and round to nearest even
+fn rshift_round(man: &BigUint, shift: usize) -> BigUint {
+    if shift == 0 {
+        return man.clone();
+    }
+    let mut res: BigUint = man >> shift;
+    let round = man.bit(shift as u64 - 1);
+    let sticky = man.trailing_zeros().unwrap_or(0) < shift as u64 - 1;
+    if round && (sticky || res.bit(0)) {
+        res += 1u32;
+    }
+    res
+}
+
+fn field(bits: &BigUint, lower: usize, width: usize) -> BigUint {
+    (bits >> lower) & ((1.to_biguint().unwrap() << width) - 1u32)
+}
+
+// widest exponent whose bias and range fit in an i64
+pub const FLOPOCO_MAX_WE: usize = 62;
+
+pub fn to_flopoco<T: FloatType>(num: &BigUint) -> BigUint {
+    to_flopoco_custom::<T>(num, T::EXP, T::SIG - 1)
+}
+
+// InputIEEE with wE exponent bits and wF fraction bits
+pub fn to_flopoco_custom<T: FloatType>(num: &BigUint, we: usize, wf: usize) -> BigUint {
+    assert!((2..=FLOPOCO_MAX_WE).contains(&we) && wf >= 1);
+    let f0: BigUint = 0.to_biguint().unwrap();
+    let f1: BigUint = 1.to_biguint().unwrap();
+    // two exn bits at the msb: 0=zero, 1=normal, 2=inf, 3=nan
+    // no subnormal numbers
+    let (sign, exp_in, sig_in) = extract::<T>(num);
+
+    let is_zero_exp_in = exp_in == f0;
+    let is_zero_sig_in = sig_in == f0;
+
+    let bias_in = (1i64 << (T::EXP - 1)) - 1;
+    let bias_out = (1i64 << (we - 1)) - 1;
+
+    let (exn, exp, sig) = if is_zero_exp_in && (is_zero_sig_in || we <= T::EXP) {
+        // zero, or subnormal
+        // like InputIEEE, subnormals are flushed to zero
+        // unless the exponent is wider than the IEEE one
+        (f0.clone(), f0.clone(), f0)
+    } else if exp_in == T::max_exp() {
+        // special
+        if is_zero_sig_in {
+            // infinity
+            (2.to_biguint().unwrap(), f0.clone(), f0)
+        } else {
+            // NaN
+            (3.to_biguint().unwrap(), f0.clone(), f0)
+        }
+    } else {
+        // normal, or subnormal to normalize
+        // value = 1.man * 2^exp
+        let (mut exp, man) = if is_zero_exp_in {
+            let n = (T::SIG - 1 - sig_in.bits() as usize) as i64;
+            let man = field(&(sig_in << (n + 1)), 0, T::SIG - 1);
+            (1 - bias_in - n - 1, man)
+        } else {
+            let exp = exp_in.iter_u64_digits().next().unwrap_or(0) as i64;
+            (exp - bias_in, sig_in)
+        };
+
+        // round 1.man to wF fraction bits
+        let norm = (&f1 << (T::SIG - 1)) | man;
+        let mut sig = if wf >= T::SIG - 1 {
+            norm << (wf - (T::SIG - 1))
+        } else {
+            rshift_round(&norm, T::SIG - 1 - wf)
+        };
+        if sig.bits() as usize > wf + 1 {
+            // carry out of rounding: 10.000 -> 1.000
+            sig >>= 1;
+            exp += 1;
+        }
+
+        let biased = exp + bias_out;
+        if biased < 0 {
+            // underflow to zero
+            (f0.clone(), f0.clone(), f0)
+        } else if biased >= (1i64 << we) {
+            // overflow to infinity
+            (2.to_biguint().unwrap(), f0.clone(), f0)
+        } else {
+            let sig = sig - (&f1 << wf);
+            (f1, biased.to_biguint().unwrap(), sig)
+        }
+    };
+    (exn << (we + wf + 1)) | (sign << (we + wf)) | (exp << wf) | sig
+}
+
+pub fn from_flopoco<T: FloatType>(bits: &BigUint) -> BigUint {
+    from_flopoco_custom::<T>(bits, T::EXP, T::SIG - 1)
+}
+
+// OutputIEEE with wE exponent bits and wF fraction bits
+pub fn from_flopoco_custom<T: FloatType>(bits: &BigUint, we: usize, wf: usize) -> BigUint {
+    assert!((2..=FLOPOCO_MAX_WE).contains(&we) && wf >= 1);
+    let f0: BigUint = 0.to_biguint().unwrap();
+    let f1: BigUint = 1.to_biguint().unwrap();
+    let exn = field(bits, we + wf + 1, 2);
+    let sign = field(bits, we + wf, 1);
+    let exp_in = field(bits, wf, we);
+    let sig_in = field(bits, 0, wf);
+
+    let bias_in = (1i64 << (we - 1)) - 1;
+    let bias_out = (1i64 << (T::EXP - 1)) - 1;
+    let inf = T::max_exp() << (T::SIG - 1);
+
+    let exn = exn.iter_u32_digits().next().unwrap_or(0);
+    let rest = if exn == 0 {
+        // zero
+        f0
+    } else if exn == 2 {
+        // infinity
+        inf
+    } else if exn == 3 {
+        // NaN: quiet NaN without payload
+        inf | (&f1 << (T::SIG - 2))
+    } else {
+        // value = 1.sig * 2^exp
+        let exp = exp_in.iter_u64_digits().next().unwrap_or(0) as i64 - bias_in;
+        let norm = (&f1 << wf) | sig_in;
+
+        // the lsb of the result is 2^(max(exp, emin) - (SIG - 1))
+        let emin = 1 - bias_out;
+        let shift = max(exp, emin) - (T::SIG as i64 - 1) - (exp - wf as i64);
+        let man = if shift > 0 {
+            rshift_round(&norm, shift as usize)
+        } else {
+            norm << (-shift) as usize
+        };
+
+        // the carry of rounding propagates into the exponent field
+        let res = if exp >= emin {
+            ((exp + bias_out).to_biguint().unwrap() << (T::SIG - 1)) + man - (&f1 << (T::SIG - 1))
+        } else {
+            man
+        };
+        if res >= inf {
+            // overflow to infinity
+            inf
+        } else {
+            res
+        }
+    };
+    (sign << (T::WIDTH - 1)) + rest
+}
+
+pub fn print_flopoco<T: FloatType>(bits: &BigUint) -> String {
+    print_flopoco_custom(bits, T::EXP, T::SIG - 1)
+}
+
+pub fn print_flopoco_custom(bits: &BigUint, we: usize, wf: usize) -> String {
+    let exn = field(bits, we + wf + 1, 2);
+    let sign = field(bits, we + wf, 1);
+    let exp = field(bits, wf, we);
+    let sig = field(bits, 0, wf);
+    format!("exn={},sign={},exp={},sig={}", exn, sign, exp, sig)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        from_flopoco, from_flopoco_custom, print_flopoco, print_flopoco_custom, to_flopoco,
+        to_flopoco_custom, FloatType, FLOPOCO_MAX_WE,
+    };
+    use num_bigint::{BigUint, ToBigUint};
+
+    #[test]
+    fn test() {
+        for a in [
+            1.0f32,
+            0.1,
+            -2.5,
+            f32::MAX,
+            f32::MIN_POSITIVE,
+            0.0,
+            -0.0,
+            f32::INFINITY,
+            -f32::INFINITY,
+        ] {
+            let flopoco = to_flopoco::<f32>(&a.to_biguint());
+            let back = f32::from_biguint(&from_flopoco::<f32>(&flopoco));
+            assert_eq!(
+                a.to_bits(),
+                back.to_bits(),
+                "{}",
+                print_flopoco::<f32>(&flopoco)
+            );
+        }
+
+        // subnormals are flushed to zero
+        for (a, expected) in [
+            (f64::from_bits(1), "exn=0,sign=0,exp=0,sig=0"),
+            (-1e-310, "exn=0,sign=1,exp=0,sig=0"),
+            (f64::NAN, "exn=3,sign=0,exp=0,sig=0"),
+        ] {
+            let flopoco = to_flopoco::<f64>(&a.to_biguint());
+            assert_eq!(print_flopoco::<f64>(&flopoco), expected);
+        }
+        let nan = from_flopoco::<f32>(&to_flopoco::<f32>(&f32::NAN.to_biguint()));
+        assert!(f32::from_biguint(&nan).is_nan());
+    }
+
+    #[test]
+    fn test_subnormal_output() {
+        let flopoco = |exp: u32, sig: u32| -> BigUint {
+            (1.to_biguint().unwrap() << 32)
+                | (exp.to_biguint().unwrap() << 23)
+                | sig.to_biguint().unwrap()
+        };
+        for (exp, sig, expected) in [
+            // 1.0 * 2^-127
+            (0, 0, f32::MIN_POSITIVE / 2.0),
+            // exact
+            (0, 0b10, f32::from_bits(0x400001)),
+            // tie, round to even
+            (0, 0b01, f32::from_bits(0x400000)),
+            (0, 0b11, f32::from_bits(0x400002)),
+            // carry into the exponent
+            (0, 0x7fffff, f32::MIN_POSITIVE),
+            // overflow
+            (0xff, 0, f32::INFINITY),
+            (0xfe, 0x7fffff, f32::MAX),
+        ] {
+            let res = f32::from_biguint(&from_flopoco::<f32>(&flopoco(exp, sig)));
+            assert_eq!(res.to_bits(), expected.to_bits());
+        }
+    }
+
+    #[test]
+    fn test_custom() {
+        // widening is exact
+        for (a, expected) in [
+            (1.0f32, "exn=1,sign=0,exp=511,sig=0"),
+            (-1.5, "exn=1,sign=1,exp=511,sig=536870912"),
+            (f32::from_bits(1), "exn=1,sign=0,exp=362,sig=0"),
+            (f32::INFINITY, "exn=2,sign=0,exp=0,sig=0"),
+        ] {
+            let flopoco = to_flopoco_custom::<f32>(&a.to_biguint(), 10, 30);
+            assert_eq!(print_flopoco_custom(&flopoco, 10, 30), expected);
+            let back = f32::from_biguint(&from_flopoco_custom::<f32>(&flopoco, 10, 30));
+            assert_eq!(a.to_bits(), back.to_bits());
+        }
+        // the widest exponent
+        for a in [1.0f64, -0.1, f64::MAX, f64::from_bits(1)] {
+            let flopoco = to_flopoco_custom::<f64>(&a.to_biguint(), FLOPOCO_MAX_WE, 52);
+            let back = from_flopoco_custom::<f64>(&flopoco, FLOPOCO_MAX_WE, 52);
+            assert_eq!(a.to_bits(), f64::from_biguint(&back).to_bits());
+        }
+
+        // narrowing
+        for (a, expected) in [
+            // round to nearest even
+            (1.0 + f64::EPSILON * 3.0, "exn=1,sign=0,exp=15,sig=0"),
+            (1.0 + 1.0 / 16.0, "exn=1,sign=0,exp=15,sig=0"),
+            (1.0 + 3.0 / 16.0, "exn=1,sign=0,exp=15,sig=2"),
+            (1.0 - 1.0 / 64.0, "exn=1,sign=0,exp=15,sig=0"),
+            // overflow to infinity
+            (1e10, "exn=2,sign=0,exp=0,sig=0"),
+            (-131072.0, "exn=2,sign=1,exp=0,sig=0"),
+            (65535.0, "exn=1,sign=0,exp=31,sig=0"),
+            // underflow to zero
+            (1e-10, "exn=0,sign=0,exp=0,sig=0"),
+            (-1e-10, "exn=0,sign=1,exp=0,sig=0"),
+            (1.0 / 32768.0, "exn=1,sign=0,exp=0,sig=0"),
+        ] {
+            let flopoco = to_flopoco_custom::<f64>(&a.to_biguint(), 5, 3);
+            assert_eq!(print_flopoco_custom(&flopoco, 5, 3), expected, "{}", a);
+        }
+
+        let mut state = 0x853c49e6748fea9bu64;
+        for _ in 0..10000 {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            // exponents around the f32 range
+            let exp = 1023 - 160 + (state >> 32) % 300;
+            let a = f64::from_bits((state & 0x800fffffffffffff) | (exp << 52));
+
+            // f64 -> (11, 52) -> f32 rounds like a native conversion
+            let flopoco = to_flopoco_custom::<f64>(&a.to_biguint(), 11, 52);
+            let res = f32::from_biguint(&from_flopoco_custom::<f32>(&flopoco, 11, 52));
+            assert_eq!(res.to_bits(), (a as f32).to_bits(), "{}", a);
+
+            // f64 -> (8, 23) matches f32 -> (8, 23) for normal f32 values
+            if (a as f32).is_normal() {
+                let flopoco = to_flopoco_custom::<f64>(&a.to_biguint(), 8, 23);
+                assert_eq!(flopoco, to_flopoco::<f32>(&(a as f32).to_biguint()));
+            }
+
+            // f32 -> (10, 30) -> f64 is exact
+            let b = a as f32;
+            let flopoco = to_flopoco_custom::<f32>(&b.to_biguint(), 10, 30);
+            let res = f64::from_biguint(&from_flopoco_custom::<f64>(&flopoco, 10, 30));
+            assert_eq!(res.to_bits(), (b as f64).to_bits());
+        }
+    }
+}
@@ -0,0 +1,571 @@
+use num_bigint::{BigInt, BigUint, Sign, ToBigUint};
+use std::cmp::Ordering;
+
+use crate::{
+    bit, extract, flush_input, hardfloat_to_raw_float, range, round_env, ExceptionFlags, FloatType,
+    FpEnv, RoundingMode,
+};
+
+pub fn to_hardfloat<T: FloatType>(num: &BigUint) -> BigUint {
+    let zero = 0.to_biguint().unwrap();
+    let one = 1.to_biguint().unwrap();
+    // http://www.jhauser.us/arithmetic/HardFloat-1/doc/HardFloat-Verilog.html
+    // recFNFromFN
+    // float32: 1+8+23
+    // hardfloat32: 1+9+23
+    // EXP=8, SIG=24
+    // k=EXP-1=7
+    let (sign, exp_in, sig_in) = extract::<T>(num);
+
+    let is_zero_exp_in = exp_in == zero;
+    let is_zero_sig_in = sig_in == zero;
+
+    let k = T::EXP - 1;
+    let pow2k = &one << k;
+    let (exp, sig) = if is_zero_exp_in && is_zero_sig_in {
+        // zero: the upper three exponent bits are 000
+        (zero.clone(), zero)
+    } else if is_zero_exp_in && !is_zero_sig_in {
+        // subnormal
+        // shift the leading one out to make it implicit
+        let n = T::SIG - 1 - sig_in.bits() as usize;
+        let exp = pow2k + 1u32 - n;
+        let sig = range::<T>(&(sig_in << (n + 1)), T::SIG - 2, 0);
+        (exp, sig)
+    } else if exp_in == T::max_exp() {
+        // special
+        if is_zero_sig_in {
+            // infinity: upper three exponent bits are 110
+            (0b110.to_biguint().unwrap() << (T::EXP - 2), zero)
+        } else {
+            // NaN: upper three exponent bits are 111, payload is kept
+            (0b111.to_biguint().unwrap() << (T::EXP - 2), sig_in)
+        }
+    } else {
+        // normal
+        let exp = exp_in + pow2k + 1u32;
+        (exp, sig_in)
+    };
+    (sign << (T::EXP + T::SIG)) | (exp << (T::SIG - 1)) | sig
+}
+
+// fNFromRecFN
+pub fn from_hardfloat<T: FloatType>(bits: &BigUint) -> BigUint {
+    let zero = 0.to_biguint().unwrap();
+    let one = 1.to_biguint().unwrap();
+    let sign = bit::<T>(bits, T::EXP + T::SIG);
+    let exp_in = range::<T>(bits, T::EXP + T::SIG - 1, T::SIG - 1);
+    let sig_in = range::<T>(bits, T::SIG - 2, 0);
+
+    let top = range::<T>(&exp_in, T::EXP, T::EXP - 2);
+    let top = top.iter_u32_digits().next().unwrap_or(0);
+    let k = T::EXP - 1;
+    let pow2k = &one << k;
+    let (exp, sig) = if top == 0b000 {
+        // zero
+        (zero.clone(), zero)
+    } else if top == 0b110 {
+        // infinity
+        (T::max_exp(), zero)
+    } else if top == 0b111 {
+        // NaN
+        (T::max_exp(), sig_in)
+    } else if exp_in > &pow2k + 1u32 {
+        // normal
+        (exp_in - pow2k - 1u32, sig_in)
+    } else {
+        // subnormal
+        // shift the implicit one back into the fraction
+        let n = (&pow2k + 2u32 - exp_in)
+            .iter_u64_digits()
+            .next()
+            .unwrap_or(0);
+        let sig = ((&one << (T::SIG - 1)) | sig_in) >> n;
+        (zero, sig)
+    };
+    (sign << (T::WIDTH - 1)) | (exp << (T::SIG - 1)) | sig
+}
+
+// returns (is_nan, is_inf, is_zero, sign, exponent, significand)
+// value = significand * 2^exponent
+fn unpack_hardfloat<T: FloatType>(bits: &BigUint) -> (bool, bool, bool, bool, i64, BigUint) {
+    let raw = hardfloat_to_raw_float::<T>(bits);
+    let exp = raw.s_exp - (1i64 << T::EXP) - (T::SIG as i64 - 1);
+    (raw.is_nan, raw.is_inf, raw.is_zero, raw.sign, exp, raw.sig)
+}
+
+// isSigNaNRawFloat
+fn is_signaling_hardfloat<T: FloatType>(bits: &BigUint) -> bool {
+    let raw = hardfloat_to_raw_float::<T>(bits);
+    raw.is_nan && !raw.sig.bit(T::SIG as u64 - 2)
+}
+
+fn ieee_hardfloat<T: FloatType>(a: &BigUint) -> T {
+    T::from_biguint(&from_hardfloat::<T>(a))
+}
+
+// DAZ on a recoded operand
+fn flush_input_hardfloat<T: FloatType>(
+    a: &BigUint,
+    env: &FpEnv,
+    flags: &mut ExceptionFlags,
+) -> BigUint {
+    if !env.daz {
+        return a.clone();
+    }
+    let res = flush_input(ieee_hardfloat::<T>(a), env, flags);
+    to_hardfloat::<T>(&res.to_biguint())
+}
+
+// default NaN of HardFloat: sign=0, exp=111..., sig=100...
+// HardFloat returns it for every nan result, whatever the nan policy of env
+fn default_nan_hardfloat<T: FloatType>() -> BigUint {
+    let one = 1.to_biguint().unwrap();
+    (0b111.to_biguint().unwrap() << (T::EXP + T::SIG - 3)) | (one << (T::SIG - 2))
+}
+
+// round the raw result and recode it, like RoundRawFNToRecFN, with FTZ
+fn round_hardfloat<T: FloatType>(
+    sign: bool,
+    exp: i64,
+    sig: &BigUint,
+    env: &FpEnv,
+) -> (BigUint, ExceptionFlags) {
+    let (res, flags) = round_env::<T>(sign, exp, sig, env);
+    (to_hardfloat::<T>(&res.to_biguint()), flags)
+}
+
+// AddRecFN
+pub fn hardfloat_add<T: FloatType>(
+    a: &BigUint,
+    b: &BigUint,
+    env: &FpEnv,
+) -> (BigUint, ExceptionFlags) {
+    add_hardfloat::<T>(false, a, b, env)
+}
+
+// AddRecFN with subOp=1
+pub fn hardfloat_sub<T: FloatType>(
+    a: &BigUint,
+    b: &BigUint,
+    env: &FpEnv,
+) -> (BigUint, ExceptionFlags) {
+    add_hardfloat::<T>(true, a, b, env)
+}
+
+// addRecFNToRaw
+fn add_hardfloat<T: FloatType>(
+    sub: bool,
+    a: &BigUint,
+    b: &BigUint,
+    env: &FpEnv,
+) -> (BigUint, ExceptionFlags) {
+    let mut flags = ExceptionFlags::default();
+    let a = &flush_input_hardfloat::<T>(a, env, &mut flags);
+    let b = &flush_input_hardfloat::<T>(b, env, &mut flags);
+    let (nan_a, inf_a, zero_a, sign_a, exp_a, sig_a) = unpack_hardfloat::<T>(a);
+    let (nan_b, inf_b, zero_b, sign_b, exp_b, sig_b) = unpack_hardfloat::<T>(b);
+    let sign_b = sign_b ^ sub;
+
+    if nan_a || nan_b {
+        flags.invalid = is_signaling_hardfloat::<T>(a) || is_signaling_hardfloat::<T>(b);
+        (default_nan_hardfloat::<T>(), flags)
+    } else if inf_a && inf_b && sign_a != sign_b {
+        // inf - inf
+        flags.invalid = true;
+        (default_nan_hardfloat::<T>(), flags)
+    } else if inf_a || inf_b {
+        let sign = if inf_a { sign_a } else { sign_b };
+        let inf = 0b110.to_biguint().unwrap() << (T::EXP + T::SIG - 3);
+        (
+            ((sign as u32).to_biguint().unwrap() << (T::EXP + T::SIG)) | inf,
+            flags,
+        )
+    } else {
+        // exact sum, aligned to the smaller exponent
+        let exp = exp_a.min(exp_b);
+        let signed = |zero: bool, sign: bool, exp_x: i64, sig: BigUint| {
+            if zero {
+                BigInt::from(0)
+            } else {
+                BigInt::from_biguint(
+                    if sign { Sign::Minus } else { Sign::Plus },
+                    sig << (exp_x - exp) as usize,
+                )
+            }
+        };
+        let sum = signed(zero_a, sign_a, exp_a, sig_a) + signed(zero_b, sign_b, exp_b, sig_b);
+        let sign = match sum.sign() {
+            Sign::Minus => true,
+            Sign::Plus => false,
+            // exact zero: -0 only for -0 + -0 or when rounding down
+            Sign::NoSign => {
+                if zero_a && zero_b && sign_a == sign_b {
+                    sign_a
+                } else {
+                    env.rounding == RoundingMode::TowardNegative
+                }
+            }
+        };
+        let (res, round_flags) = round_hardfloat::<T>(sign, exp, sum.magnitude(), env);
+        (res, flags | round_flags)
+    }
+}
+
+// MulRecFN
+pub fn hardfloat_mul<T: FloatType>(
+    a: &BigUint,
+    b: &BigUint,
+    env: &FpEnv,
+) -> (BigUint, ExceptionFlags) {
+    let mut flags = ExceptionFlags::default();
+    let a = &flush_input_hardfloat::<T>(a, env, &mut flags);
+    let b = &flush_input_hardfloat::<T>(b, env, &mut flags);
+    let (nan_a, inf_a, zero_a, sign_a, exp_a, sig_a) = unpack_hardfloat::<T>(a);
+    let (nan_b, inf_b, zero_b, sign_b, exp_b, sig_b) = unpack_hardfloat::<T>(b);
+    let sign = sign_a ^ sign_b;
+
+    if nan_a || nan_b {
+        flags.invalid = is_signaling_hardfloat::<T>(a) || is_signaling_hardfloat::<T>(b);
+        (default_nan_hardfloat::<T>(), flags)
+    } else if (inf_a && zero_b) || (zero_a && inf_b) {
+        // inf * 0
+        flags.invalid = true;
+        (default_nan_hardfloat::<T>(), flags)
+    } else if inf_a || inf_b {
+        let inf = 0b110.to_biguint().unwrap() << (T::EXP + T::SIG - 3);
+        (
+            ((sign as u32).to_biguint().unwrap() << (T::EXP + T::SIG)) | inf,
+            flags,
+        )
+    } else if zero_a || zero_b {
+        (
+            ((sign as u32).to_biguint().unwrap() << (T::EXP + T::SIG)),
+            flags,
+        )
+    } else {
+        let (res, round_flags) = round_hardfloat::<T>(sign, exp_a + exp_b, &(sig_a * sig_b), env);
+        (res, flags | round_flags)
+    }
+}
+
+// CompareRecFN
+// returns (lt, eq, gt, unordered, flags)
+// signaling comparisons raise invalid on any NaN, quiet ones only on signaling NaNs
+pub fn hardfloat_compare<T: FloatType>(
+    a: &BigUint,
+    b: &BigUint,
+    signaling: bool,
+    env: &FpEnv,
+) -> (bool, bool, bool, bool, ExceptionFlags) {
+    let mut flags = ExceptionFlags::default();
+    let a = &flush_input_hardfloat::<T>(a, env, &mut flags);
+    let b = &flush_input_hardfloat::<T>(b, env, &mut flags);
+    let (nan_a, inf_a, zero_a, sign_a, exp_a, sig_a) = unpack_hardfloat::<T>(a);
+    let (nan_b, inf_b, zero_b, sign_b, exp_b, sig_b) = unpack_hardfloat::<T>(b);
+
+    let ordered = !nan_a && !nan_b;
+    flags.invalid =
+        is_signaling_hardfloat::<T>(a) || is_signaling_hardfloat::<T>(b) || (signaling && !ordered);
+    if !ordered {
+        return (false, false, false, true, flags);
+    }
+
+    // compare magnitudes: zero < finite < inf
+    let magnitude = |inf: bool, zero: bool, exp: i64, sig: BigUint| {
+        (
+            !zero as u8 + inf as u8,
+            if inf || zero { 0 } else { exp },
+            sig,
+        )
+    };
+    let mag_a = magnitude(inf_a, zero_a, exp_a, sig_a);
+    let mag_b = magnitude(inf_b, zero_b, exp_b, sig_b);
+    let order = if zero_a && zero_b {
+        Ordering::Equal
+    } else {
+        match (sign_a, sign_b) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => mag_a.cmp(&mag_b),
+            (true, true) => mag_b.cmp(&mag_a),
+        }
+    };
+    (
+        order == Ordering::Less,
+        order == Ordering::Equal,
+        order == Ordering::Greater,
+        false,
+        flags,
+    )
+}
+
+pub fn print_hardfloat<T: FloatType>(bits: &BigUint) -> String {
+    let sign = bit::<T>(bits, T::SIG + T::EXP);
+    let exp = range::<T>(bits, T::SIG + T::EXP - 1, T::SIG - 1);
+    let sig = range::<T>(bits, T::SIG - 2, 0);
+    format!("sign={},exp={},sig={}", sign, exp, sig)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        from_hardfloat, hardfloat_add, hardfloat_compare, hardfloat_mul, hardfloat_sub,
+        print_hardfloat, softfloat_add, softfloat_sub, to_hardfloat, FloatType, FpEnv, NanPolicy,
+        RoundingMode,
+    };
+    use half::f16;
+    use num_bigint::{BigUint, ToBigUint};
+
+    // bit-level port of recFNFromFN/rawFloatFromFN from berkeley-hardfloat
+    fn rec_fn_from_fn(exp_width: usize, sig_width: usize, num: u128) -> u128 {
+        let mask = |width: usize| (1u128 << width) - 1;
+        let sign = (num >> (exp_width + sig_width - 1)) & 1;
+        let exp_in = (num >> (sig_width - 1)) & mask(exp_width);
+        let fract_in = num & mask(sig_width - 1);
+        let is_zero_exp_in = exp_in == 0;
+        let is_zero_fract_in = fract_in == 0;
+
+        // countLeadingZeros is a PriorityEncoder, which returns the last index
+        // when no bit is set
+        let norm_dist = (0..sig_width - 1)
+            .find(|i| (fract_in >> (sig_width - 2 - i)) & 1 == 1)
+            .unwrap_or(sig_width - 2) as u128;
+        let subnorm_fract = ((fract_in << norm_dist) & mask(sig_width - 2)) << 1;
+        let adjusted_exp = ((if is_zero_exp_in {
+            norm_dist ^ mask(exp_width + 1)
+        } else {
+            exp_in
+        }) + ((1 << (exp_width - 1)) | if is_zero_exp_in { 2 } else { 1 }))
+            & mask(exp_width + 1);
+        let is_zero = is_zero_exp_in && is_zero_fract_in;
+        let is_special = (adjusted_exp >> (exp_width - 1)) == 0b11;
+        let is_nan = is_special && !is_zero_fract_in;
+
+        let exp_hi = (if is_zero {
+            0
+        } else {
+            adjusted_exp >> (exp_width - 2)
+        }) | is_nan as u128;
+        let exp_lo = adjusted_exp & mask(exp_width - 2);
+        let fract = if is_zero_exp_in {
+            subnorm_fract
+        } else {
+            fract_in
+        };
+        (sign << (exp_width + sig_width))
+            | (exp_hi << (exp_width + sig_width - 3))
+            | (exp_lo << (sig_width - 1))
+            | fract
+    }
+
+    fn check<T: FloatType>(num: u128) {
+        let expected = rec_fn_from_fn(T::EXP, T::SIG, num);
+        let actual = to_hardfloat::<T>(&num.to_biguint().unwrap());
+        let actual = u128::try_from(&actual).unwrap();
+        // the lower exponent bits of a recoded zero are don't-care
+        let exp_hi_shift = T::EXP + T::SIG - 3;
+        let masked = |bits: u128| {
+            if (bits >> exp_hi_shift) & 0b111 == 0 {
+                bits & !(((1u128 << (T::EXP - 2)) - 1) << (T::SIG - 1))
+            } else {
+                bits
+            }
+        };
+        assert_eq!(
+            masked(expected),
+            masked(actual),
+            "{}: {:#x} recoded to {} instead of {}",
+            T::name(),
+            num,
+            print_hardfloat::<T>(&actual.to_biguint().unwrap()),
+            print_hardfloat::<T>(&expected.to_biguint().unwrap()),
+        );
+    }
+
+    #[test]
+    fn test_f16() {
+        for num in 0..=u16::MAX {
+            check::<f16>(num as u128);
+        }
+    }
+
+    #[test]
+    fn test_f32_f64() {
+        // sample with a fixed LCG, and walk the special exponents on purpose
+        let mut state = 0x2545f4914f6cdd1du64;
+        for _ in 0..100000 {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            check::<f32>((state >> 32) as u128);
+            check::<f64>(state as u128);
+            for exp in [0, 0xff] {
+                check::<f32>(((state >> 41) | (exp << 23)) as u128);
+            }
+            for exp in [0, 0x7ff] {
+                check::<f64>(((state >> 12) | (exp << 52)) as u128);
+            }
+        }
+    }
+
+    #[test]
+    fn test_special() {
+        for (num, expected) in [
+            (f64::INFINITY, "sign=0,exp=3072,sig=0"),
+            (-f64::INFINITY, "sign=1,exp=3072,sig=0"),
+            (f64::NAN, "sign=0,exp=3584,sig=2251799813685248"),
+            (0.0, "sign=0,exp=0,sig=0"),
+            (1.0, "sign=0,exp=2048,sig=0"),
+            (f64::from_bits(1), "sign=0,exp=974,sig=0"),
+        ] {
+            let hardfloat = to_hardfloat::<f64>(&num.to_biguint());
+            assert_eq!(print_hardfloat::<f64>(&hardfloat), expected);
+        }
+    }
+
+    fn sample_f32(state: &mut u64) -> f32 {
+        *state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        let bits = (*state >> 32) as u32;
+        // bias towards small exponent differences and special values
+        match *state % 8 {
+            0 => f32::from_bits(bits & 0x807fffff),
+            1 => f32::from_bits(bits | 0x7f800000),
+            2 => f32::from_bits((bits & 0x80ffffff) | 0x3f000000),
+            _ => f32::from_bits(bits),
+        }
+    }
+
+    #[test]
+    fn test_from_hardfloat() {
+        for num in 0..=u16::MAX {
+            let bits = num.to_biguint().unwrap();
+            assert_eq!(from_hardfloat::<f16>(&to_hardfloat::<f16>(&bits)), bits);
+        }
+    }
+
+    #[test]
+    fn test_arith() {
+        let env = FpEnv::default();
+        let mut state = 0x9e3779b97f4a7c15u64;
+        for _ in 0..20000 {
+            let a = sample_f32(&mut state);
+            let b = sample_f32(&mut state);
+            let rec_a = to_hardfloat::<f32>(&a.to_biguint());
+            let rec_b = to_hardfloat::<f32>(&b.to_biguint());
+
+            for (op, rec, soft, native) in [
+                (
+                    "+",
+                    hardfloat_add::<f32>(&rec_a, &rec_b, &env).0,
+                    Some(softfloat_add(a, b, &env).0),
+                    a + b,
+                ),
+                (
+                    "-",
+                    hardfloat_sub::<f32>(&rec_a, &rec_b, &env).0,
+                    Some(softfloat_sub(a, b, &env).0),
+                    a - b,
+                ),
+                (
+                    "*",
+                    hardfloat_mul::<f32>(&rec_a, &rec_b, &env).0,
+                    None,
+                    a * b,
+                ),
+            ] {
+                let res = f32::from_biguint(&from_hardfloat::<f32>(&rec));
+                if native.is_nan() {
+                    assert!(res.is_nan(), "{} {} {}", a, op, b);
+                    continue;
+                }
+                assert_eq!(res.to_bits(), native.to_bits(), "{} {} {}", a, op, b);
+                if let Some(soft) = soft {
+                    assert_eq!(res.to_bits(), soft.to_bits(), "{} {} {}", a, op, b);
+                }
+            }
+
+            let (lt, eq, gt, unordered, flags) =
+                hardfloat_compare::<f32>(&rec_a, &rec_b, false, &env);
+            assert_eq!(lt, a < b);
+            assert_eq!(eq, a == b);
+            assert_eq!(gt, a > b);
+            assert_eq!(unordered, a.is_nan() || b.is_nan());
+            let (_, _, _, _, signaling_flags) =
+                hardfloat_compare::<f32>(&rec_a, &rec_b, true, &env);
+            assert_eq!(signaling_flags.invalid, unordered);
+            assert!(flags.invalid <= unordered);
+        }
+    }
+
+    #[test]
+    fn test_flags() {
+        let rec = |a: f32| to_hardfloat::<f32>(&a.to_biguint());
+        let ieee = |a: &BigUint| f32::from_biguint(&from_hardfloat::<f32>(a));
+        let env = |rounding| FpEnv {
+            rounding,
+            ..Default::default()
+        };
+        // inf - inf is invalid and returns the default NaN
+        let (res, flags) =
+            hardfloat_add::<f32>(&rec(f32::INFINITY), &rec(-f32::INFINITY), &FpEnv::default());
+        assert_eq!(flags.to_bits(), 0b10000);
+        assert_eq!(print_hardfloat::<f32>(&res), "sign=0,exp=448,sig=4194304");
+        // nan operands do not propagate their payload, in any nan policy
+        for nan_policy in [NanPolicy::X86, NanPolicy::Arm, NanPolicy::Cuda] {
+            let env = FpEnv {
+                nan_policy,
+                ..Default::default()
+            };
+            let (res, _) = hardfloat_mul::<f32>(&rec(2.0), &rec(f32::from_bits(0x7f801234)), &env);
+            assert_eq!(ieee(&res).to_bits(), 0x7fc00000);
+        }
+        // signaling NaN
+        let snan = rec(f32::from_bits(0x7f800001));
+        let (_, flags) = hardfloat_mul::<f32>(&snan, &rec(1.0), &env(RoundingMode::NearestEven));
+        assert_eq!(flags.to_bits(), 0b10000);
+        let (_, _, _, unordered, flags) =
+            hardfloat_compare::<f32>(&snan, &rec(1.0), false, &FpEnv::default());
+        assert!(unordered && flags.invalid);
+        // x - x is -0 when rounding down
+        let (res, _) =
+            hardfloat_sub::<f32>(&rec(1.5), &rec(1.5), &env(RoundingMode::TowardNegative));
+        assert_eq!(
+            f32::from_biguint(&from_hardfloat::<f32>(&res)).to_bits(),
+            (-0.0f32).to_bits()
+        );
+        // directed rounding and overflow
+        let (res, flags) =
+            hardfloat_mul::<f32>(&rec(f32::MAX), &rec(2.0), &env(RoundingMode::TowardZero));
+        assert_eq!(f32::from_biguint(&from_hardfloat::<f32>(&res)), f32::MAX);
+        assert_eq!(flags.to_bits(), 0b00101);
+        let (res, flags) =
+            hardfloat_add::<f32>(&rec(1.0), &rec(1e-10), &env(RoundingMode::TowardPositive));
+        assert_eq!(
+            f32::from_biguint(&from_hardfloat::<f32>(&res)),
+            1.0 + f32::EPSILON
+        );
+        assert_eq!(flags.to_bits(), 0b00001);
+        // underflow
+        let (_, flags) =
+            hardfloat_mul::<f32>(&rec(1e-30), &rec(1e-10), &env(RoundingMode::NearestEven));
+        assert_eq!(flags.to_bits(), 0b00011);
+        // DAZ and FTZ
+        let flush = FpEnv {
+            daz: true,
+            ftz: true,
+            ..Default::default()
+        };
+        let tiny = rec(f32::from_bits(1));
+        let (res, flags) = hardfloat_add::<f32>(&tiny, &rec(-0.0), &flush);
+        assert_eq!(ieee(&res).to_bits(), 0);
+        assert_eq!(flags.to_bits(), 0b00000);
+        let (res, flags) = hardfloat_mul::<f32>(&rec(f32::MIN_POSITIVE), &rec(-0.5), &flush);
+        assert_eq!(ieee(&res).to_bits(), 0x80000000);
+        assert_eq!(flags.to_bits(), 0b00011);
+        let (_, eq, _, _, _) = hardfloat_compare::<f32>(&tiny, &rec(0.0), false, &flush);
+        assert!(eq);
+    }
+}
@@ -0,0 +1,170 @@
+use num_bigint::{BigInt, BigUint};
+
+use crate::{
+    decode, extract, literal_rational, parse_literal, round_literal, ExceptionFlags, FloatType,
+    FpEnv,
+};
+
+// parse a C99 hex float literal like "-0x1.8p+3", "inf" or "nan"
+// and round it to the format T
+// the binary exponent is optional like in strtod
+pub fn parse_hex_float<T: FloatType>(s: &str, env: &FpEnv) -> Option<(T, ExceptionFlags)> {
+    let literal = parse_literal(s, true)?;
+    Some(round_literal::<T>(literal, true, env))
+}
+
+// exact value of a hex float literal as num / den
+pub fn hex_float_to_rational(s: &str) -> Option<(BigInt, BigUint)> {
+    literal_rational(parse_literal(s, true)?, true)
+}
+
+// print like C99 printf("%a"), with the fraction padded to whole hex digits
+// and its trailing zeros removed
+// subnormal numbers are printed as 0x0.xxxp-emin
+pub fn print_hex_float<T: FloatType>(bits: &BigUint) -> String {
+    let (sign, exp, man) = extract::<T>(bits);
+    let sign = if sign.bits() != 0 { "-" } else { "" };
+    if exp == T::max_exp() {
+        return if man.bits() != 0 {
+            "nan".to_string()
+        } else {
+            format!("{}inf", sign)
+        };
+    }
+    if exp.bits() == 0 && man.bits() == 0 {
+        return format!("{}0x0p+0", sign);
+    }
+
+    let (_, e, _) = decode::<T>(bits);
+    let lead = if exp.bits() == 0 { 0 } else { 1 };
+    let e = e + T::SIG as i64 - 1;
+    let width = (T::SIG - 1).div_ceil(4);
+    let fract = man << (width * 4 - (T::SIG - 1));
+    let fract = format!("{:0width$x}", fract, width = width);
+    let fract = fract.trim_end_matches('0');
+    let point = if fract.is_empty() { "" } else { "." };
+    format!("{}0x{}{}{}p{:+}", sign, lead, point, fract, e)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse_hex_float, print_hex_float, CustomFloat, FloatType, FpEnv, RoundingMode};
+    use half::f16;
+
+    fn parse<T: FloatType>(s: &str) -> T {
+        parse_hex_float::<T>(s, &FpEnv::default()).unwrap().0
+    }
+
+    #[test]
+    fn test() {
+        for (s, expected) in [
+            ("0x1.8p+3", 12.0f64),
+            ("-0x1.8p3", -12.0),
+            ("0X1P-1", 0.5),
+            ("0x.8p1", 1.0),
+            ("0x10", 16.0),
+            ("0x1.", 1.0),
+            ("0x0p+0", 0.0),
+            ("-0x0.0p0", -0.0),
+            ("0x1.fffffffffffffp+1023", f64::MAX),
+            ("0x1p+1024", f64::INFINITY),
+            ("0x0.0000000000001p-1022", f64::from_bits(1)),
+            ("0x1p-1075", 0.0),
+            ("0x1.8p-1075", f64::from_bits(1)),
+            ("0x1p-99999999999999", 0.0),
+            ("0x1p+99999999999999", f64::INFINITY),
+            ("-inf", f64::NEG_INFINITY),
+        ] {
+            assert_eq!(parse::<f64>(s).to_bits(), expected.to_bits(), "{}", s);
+        }
+        assert!(parse::<f64>("nan").is_nan());
+        for s in [
+            "",
+            "0x",
+            "0x.",
+            "0xp1",
+            "1.8p3",
+            "0x1.8p",
+            "0x1g",
+            "0x1.8e+3p0",
+        ] {
+            assert!(
+                parse_hex_float::<f64>(s, &FpEnv::default()).is_none(),
+                "{}",
+                s
+            );
+        }
+
+        for (num, expected) in [
+            (12.0f64, "0x1.8p+3"),
+            (-0.5, "-0x1p-1"),
+            (0.1, "0x1.999999999999ap-4"),
+            (0.0, "0x0p+0"),
+            (-0.0, "-0x0p+0"),
+            (f64::MAX, "0x1.fffffffffffffp+1023"),
+            (f64::from_bits(1), "0x0.0000000000001p-1022"),
+            (f64::NEG_INFINITY, "-inf"),
+            (f64::NAN, "nan"),
+        ] {
+            assert_eq!(print_hex_float::<f64>(&num.to_biguint()), expected);
+        }
+        assert_eq!(
+            print_hex_float::<f32>(&0.1f32.to_biguint()),
+            "0x1.99999ap-4"
+        );
+        let num = f16::from_f32(0.1);
+        assert_eq!(print_hex_float::<f16>(&num.to_biguint()), "0x1.998p-4");
+        let num = f16::from_bits(1);
+        assert_eq!(print_hex_float::<f16>(&num.to_biguint()), "0x0.004p-14");
+
+        // round trip
+        for num in 0..=u16::MAX {
+            let num = f16::from_bits(num);
+            if num.is_nan() {
+                continue;
+            }
+            let s = print_hex_float::<f16>(&num.to_biguint());
+            assert_eq!(parse::<f16>(&s).to_bits(), num.to_bits(), "{}", s);
+        }
+        let mut state = 0x2545f4914f6cdd1du64;
+        for _ in 0..2000 {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let a = f64::from_bits(state);
+            if a.is_nan() {
+                continue;
+            }
+            let s = print_hex_float::<f64>(&a.to_biguint());
+            assert_eq!(parse::<f64>(&s).to_bits(), a.to_bits(), "{}", s);
+            let res: CustomFloat<11, 53> = parse(&s);
+            assert_eq!(res.0 as u64, a.to_bits(), "{}", s);
+            // narrowing rounds like the conversion
+            assert_eq!(parse::<f32>(&s).to_bits(), (a as f32).to_bits(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_rounding() {
+        let env = |rounding| FpEnv {
+            rounding,
+            ..Default::default()
+        };
+        // 1 + 2^-24 is halfway between two f32 numbers
+        assert_eq!(parse::<f32>("0x1.000001p0"), 1.0);
+        assert_eq!(parse::<f32>("0x1.000003p0"), 1.0 + 2.0 * f32::EPSILON);
+        assert_eq!(parse::<f32>("0x1.0000010000000001p0"), 1.0 + f32::EPSILON);
+        let (res, flags) =
+            parse_hex_float::<f32>("0x1.000001p0", &env(RoundingMode::TowardPositive)).unwrap();
+        assert_eq!(res, 1.0 + f32::EPSILON);
+        assert_eq!(flags.to_bits(), 0b00001);
+        let (res, flags) =
+            parse_hex_float::<f16>("0x1p16", &env(RoundingMode::NearestEven)).unwrap();
+        assert_eq!(res, f16::INFINITY);
+        assert_eq!(flags.to_bits(), 0b00101);
+        let (res, flags) =
+            parse_hex_float::<f16>("0x1.8p-25", &env(RoundingMode::NearestEven)).unwrap();
+        assert_eq!(res, f16::from_bits(1));
+        assert_eq!(flags.to_bits(), 0b00011);
+    }
+}
@@ -0,0 +1,184 @@
+use num_bigint::{BigUint, ToBigUint};
+
+use crate::{FloatType, CUSTOM_MAX_EXP};
+
+// exact value sig * 2^exp and its encoding
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FormatValue {
+    pub sig: BigUint,
+    pub exp: i64,
+    pub bits: BigUint,
+}
+
+// characteristics of a format with EXP exponent bits
+// and SIG significand bits including the implicit bit
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FormatInfo {
+    pub exp_bits: usize,
+    pub sig_bits: usize,
+    pub width: usize,
+    pub bias: i64,
+    // exponents of the smallest and largest normal numbers
+    pub emin: i64,
+    pub emax: i64,
+    // decimal digits that survive a round trip through the format
+    pub digits: u32,
+    // decimal digits needed to round trip any number of the format
+    pub max_digits: u32,
+    // difference between 1.0 and the next larger number
+    pub epsilon: FormatValue,
+    // None without fraction bits, when there are no subnormal numbers
+    pub min_subnormal: Option<FormatValue>,
+    pub min_normal: FormatValue,
+    pub max_finite: FormatValue,
+    // every integer from 0 up to this is exact
+    pub max_consecutive_integer: FormatValue,
+}
+
+pub fn format_info<T: FloatType>() -> FormatInfo {
+    format_info_custom(T::EXP, T::SIG)
+}
+
+// the exponent is limited like CustomFloat so that values stay printable
+pub fn format_info_custom(exp_bits: usize, sig_bits: usize) -> FormatInfo {
+    assert!((2..=CUSTOM_MAX_EXP).contains(&exp_bits) && sig_bits >= 1);
+    let one = 1.to_biguint().unwrap();
+    let bias = (1i64 << (exp_bits - 1)) - 1;
+    let emin = 1 - bias;
+    let emax = bias;
+    let prec = sig_bits as i64;
+
+    // encode sig * 2^exp, which must be exactly representable
+    let encode = |sig: BigUint, exp: i64| {
+        // shift the leading one to the implicit bit
+        let shift = prec - sig.bits() as i64;
+        let man = &sig << shift as usize;
+        let e = exp - shift + prec - 1;
+        let bits = if e >= emin {
+            let man = man - (&one << (sig_bits - 1));
+            ((e + bias).to_biguint().unwrap() << (sig_bits - 1)) | man
+        } else {
+            man >> (emin - e) as usize
+        };
+        FormatValue { sig, exp, bits }
+    };
+
+    let max_sig = (&one << sig_bits) - &one;
+    let max_finite = encode(max_sig.clone(), emax - (prec - 1));
+    // 2^SIG, or the integer part of the largest finite number
+    // if the format is too narrow for 2^SIG
+    let max_consecutive_integer = if prec <= emax {
+        encode(one.clone(), prec)
+    } else {
+        encode(max_sig >> (prec - 1 - emax) as usize, 0)
+    };
+
+    let log10_2 = std::f64::consts::LOG10_2;
+    FormatInfo {
+        exp_bits,
+        sig_bits,
+        width: exp_bits + sig_bits,
+        bias,
+        emin,
+        emax,
+        digits: ((prec - 1) as f64 * log10_2).floor() as u32,
+        max_digits: (prec as f64 * log10_2).ceil() as u32 + 1,
+        epsilon: encode(one.clone(), 1 - prec),
+        min_subnormal: (prec > 1).then(|| encode(one.clone(), emin - (prec - 1))),
+        min_normal: encode(one.clone(), emin),
+        max_finite,
+        max_consecutive_integer,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{format_info, format_info_custom, print_dyadic, FloatType};
+    use half::{bf16, f16};
+    use num_bigint::BigUint;
+
+    #[test]
+    fn test() {
+        let info = format_info::<f32>();
+        assert_eq!((info.bias, info.emin, info.emax), (127, -126, 127));
+        assert_eq!(
+            (info.emin, info.emax),
+            (f32::MIN_EXP as i64 - 1, f32::MAX_EXP as i64 - 1)
+        );
+        assert_eq!((info.digits, info.max_digits), (f32::DIGITS, 9));
+        assert_eq!(info.epsilon.bits, f32::EPSILON.to_biguint());
+        assert_eq!(
+            info.min_subnormal.unwrap().bits,
+            f32::from_bits(1).to_biguint()
+        );
+        assert_eq!(info.min_normal.bits, f32::MIN_POSITIVE.to_biguint());
+        assert_eq!(info.max_finite.bits, f32::MAX.to_biguint());
+        assert_eq!(info.max_consecutive_integer.bits, 16777216f32.to_biguint());
+        assert_eq!(
+            print_dyadic(&info.max_finite.sig, info.max_finite.exp),
+            format!("{:.0}", f32::MAX)
+        );
+        assert_eq!(
+            print_dyadic(&info.epsilon.sig, info.epsilon.exp),
+            "0.00000011920928955078125"
+        );
+
+        let info = format_info::<f64>();
+        assert_eq!(
+            (info.emin, info.emax),
+            (f64::MIN_EXP as i64 - 1, f64::MAX_EXP as i64 - 1)
+        );
+        assert_eq!((info.digits, info.max_digits), (f64::DIGITS, 17));
+        assert_eq!(info.epsilon.bits, f64::EPSILON.to_biguint());
+        assert_eq!(
+            info.min_subnormal.unwrap().bits,
+            f64::from_bits(1).to_biguint()
+        );
+        assert_eq!(info.min_normal.bits, f64::MIN_POSITIVE.to_biguint());
+        assert_eq!(info.max_finite.bits, f64::MAX.to_biguint());
+        assert_eq!(
+            info.max_consecutive_integer.bits,
+            9007199254740992f64.to_biguint()
+        );
+
+        for (info, epsilon, min_normal, max, digits) in [
+            (
+                format_info::<f16>(),
+                f16::EPSILON.to_biguint(),
+                f16::MIN_POSITIVE.to_biguint(),
+                f16::MAX.to_biguint(),
+                f16::DIGITS,
+            ),
+            (
+                format_info::<bf16>(),
+                bf16::EPSILON.to_biguint(),
+                bf16::MIN_POSITIVE.to_biguint(),
+                bf16::MAX.to_biguint(),
+                bf16::DIGITS,
+            ),
+        ] {
+            assert_eq!(info.epsilon.bits, epsilon);
+            assert_eq!(info.min_normal.bits, min_normal);
+            assert_eq!(info.max_finite.bits, max);
+            assert_eq!(info.digits, digits);
+        }
+
+        // 2^SIG is larger than the largest finite number 15.9375
+        let info = format_info_custom(3, 8);
+        assert_eq!(info.max_finite.bits, BigUint::from(0x37fu32));
+        assert_eq!(info.max_consecutive_integer.sig, BigUint::from(15u32));
+        assert_eq!(info.max_consecutive_integer.bits, BigUint::from(0x370u32));
+        // epsilon 2^-7 is subnormal
+        assert_eq!(info.epsilon.bits, BigUint::from(4u32));
+        assert_eq!(info.min_subnormal.unwrap().bits, BigUint::from(1u32));
+
+        // no fraction bits, so no subnormal numbers
+        let info = format_info_custom(4, 1);
+        assert_eq!(info.min_subnormal, None);
+        assert_eq!(info.min_normal.bits, BigUint::from(1u32));
+        assert_eq!(
+            print_dyadic(&info.max_finite.sig, info.max_finite.exp),
+            "128"
+        );
+    }
+}
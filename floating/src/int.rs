@@ -0,0 +1,234 @@
+use num_bigint::{BigInt, BigUint, Sign, ToBigUint};
+
+use crate::{
+    decode, extract, flush_input, round_at, round_pack, ExceptionFlags, FloatType, FpEnv,
+    RoundingMode,
+};
+
+// result of float to integer conversion for nan and out of range inputs,
+// all of them raise invalid
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Saturation {
+    // "integer indefinite": the minimum signed or the maximum unsigned integer
+    X86,
+    // saturate to the minimum or maximum, nan goes to the maximum
+    #[default]
+    RiscV,
+    // saturate to the minimum or maximum, nan goes to 0
+    Arm,
+}
+
+// convert an integer to T
+pub fn int_to_float<T: FloatType>(value: &BigInt, env: &FpEnv) -> (T, ExceptionFlags) {
+    let sign = value.sign() == Sign::Minus;
+    let (bits, flags) = round_pack::<T>(sign, 0, value.magnitude(), env.rounding, env.tininess);
+    (T::from_biguint(&bits), flags)
+}
+
+// convert a to a signed or unsigned integer of 8 to 128 bits,
+// rm is the direction of the operation rather than the dynamic one of env
+pub fn float_to_int<T: FloatType>(
+    a: T,
+    signed: bool,
+    width: usize,
+    rm: RoundingMode,
+    env: &FpEnv,
+) -> (BigInt, ExceptionFlags) {
+    assert!((8..=128).contains(&width));
+    let one = 1.to_biguint().unwrap();
+    let mut input_flags = ExceptionFlags::default();
+    let num_a = flush_input(a, env, &mut input_flags).to_biguint();
+    let (_, field, man) = extract::<T>(&num_a);
+    let is_nan = field == T::max_exp() && man.bits() != 0;
+    let is_inf = field == T::max_exp() && man.bits() == 0;
+    let (sign, exp, sig) = decode::<T>(&num_a);
+
+    let (min, max) = if signed {
+        (
+            -BigInt::from(&one << (width - 1)),
+            BigInt::from((&one << (width - 1)) - &one),
+        )
+    } else {
+        (BigInt::default(), BigInt::from((&one << width) - &one))
+    };
+    let invalid = ExceptionFlags {
+        invalid: true,
+        ..Default::default()
+    };
+
+    let (value, inexact) = if is_nan || is_inf {
+        (BigInt::default(), false)
+    } else {
+        let (magnitude, inexact) = round_at(sign, &sig, exp, 0, rm);
+        let sign = if sign { Sign::Minus } else { Sign::Plus };
+        (BigInt::from_biguint(sign, magnitude), inexact)
+    };
+    if is_nan || is_inf || value < min || value > max {
+        let res = match env.saturation {
+            Saturation::X86 if signed => min,
+            Saturation::X86 => max,
+            Saturation::Arm if is_nan => BigInt::default(),
+            _ if is_nan => max,
+            _ if sign => min,
+            _ => max,
+        };
+        return (res, invalid | input_flags);
+    }
+    let flags = ExceptionFlags {
+        inexact,
+        ..input_flags
+    };
+    (value, flags)
+}
+
+// BigUint bits of a two's complement integer
+pub fn int_to_bits(value: &BigInt, width: usize) -> BigUint {
+    let modulus = BigInt::from(1.to_biguint().unwrap() << width);
+    let bits = ((value % &modulus) + &modulus) % &modulus;
+    bits.magnitude().clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        float_to_int, int_to_bits, int_to_float, FpEnv, Profile, RoundingMode, Saturation,
+    };
+    use half::f16;
+    use num_bigint::{BigInt, BigUint};
+
+    #[test]
+    fn test_int_to_float() {
+        let rne = FpEnv::default();
+        let rup = FpEnv {
+            rounding: RoundingMode::TowardPositive,
+            ..Default::default()
+        };
+        let mut state = 0x2545f4914f6cdd1du64;
+        for _ in 0..10000 {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            // various magnitudes
+            let a = (state as i64) >> (state % 64);
+            let b = ((state as u128) << 64 | state.rotate_left(17) as u128) >> (state % 128);
+            let (res, _) = int_to_float::<f32>(&BigInt::from(a), &rne);
+            assert_eq!(res.to_bits(), (a as f32).to_bits(), "{}", a);
+            let (res, _) = int_to_float::<f64>(&BigInt::from(a), &rne);
+            assert_eq!(res.to_bits(), (a as f64).to_bits(), "{}", a);
+            let (res, _) = int_to_float::<f32>(&BigInt::from(b), &rne);
+            assert_eq!(res.to_bits(), (b as f32).to_bits(), "{}", b);
+            let (res, _) = int_to_float::<f64>(&BigInt::from(b as i128), &rne);
+            assert_eq!(res.to_bits(), ((b as i128) as f64).to_bits(), "{}", b);
+        }
+
+        // flags and rounding modes
+        let (res, flags) = int_to_float::<f32>(&BigInt::from(16777217), &rne);
+        assert_eq!((res, flags.to_bits()), (16777216.0, 0b00001));
+        let (res, _) = int_to_float::<f32>(&BigInt::from(16777217), &rup);
+        assert_eq!(res, 16777218.0);
+        let (res, _) = int_to_float::<f32>(&BigInt::from(-16777217), &rup);
+        assert_eq!(res, -16777216.0);
+        let (res, flags) = int_to_float::<f16>(&BigInt::from(u128::MAX), &rne);
+        assert_eq!((res, flags.to_bits()), (f16::INFINITY, 0b00101));
+        let (res, flags) = int_to_float::<f64>(&BigInt::from(0), &rne);
+        assert_eq!((res.to_bits(), flags.to_bits()), (0, 0));
+    }
+
+    #[test]
+    fn test_float_to_int() {
+        let rtz = RoundingMode::TowardZero;
+        // Rust casts saturate with nan going to 0 like ARM
+        let env = FpEnv {
+            saturation: Saturation::Arm,
+            ..Default::default()
+        };
+        let mut state = 0x2545f4914f6cdd1du64;
+        for _ in 0..10000 {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            // exponents around the integer ranges
+            let a =
+                f64::from_bits((state & 0x800fffffffffffff) | ((1000 + (state >> 52) % 160) << 52));
+            let b = if state.is_multiple_of(100) {
+                f64::NAN
+            } else {
+                a
+            };
+            for b in [b, b as f32 as f64] {
+                let to_int = |signed, width| float_to_int(b, signed, width, rtz, &env).0;
+                assert_eq!(to_int(true, 8), BigInt::from(b as i8));
+                assert_eq!(to_int(false, 8), BigInt::from(b as u8));
+                assert_eq!(to_int(true, 16), BigInt::from(b as i16));
+                assert_eq!(to_int(false, 16), BigInt::from(b as u16));
+                assert_eq!(to_int(true, 32), BigInt::from(b as i32));
+                assert_eq!(to_int(false, 32), BigInt::from(b as u32));
+                assert_eq!(to_int(true, 64), BigInt::from(b as i64));
+                assert_eq!(to_int(false, 64), BigInt::from(b as u64));
+                assert_eq!(to_int(true, 128), BigInt::from(b as i128));
+                assert_eq!(to_int(false, 128), BigInt::from(b as u128));
+                let (_, flags) = float_to_int(b, true, 32, rtz, &env);
+                let in_range = b.trunc() >= i32::MIN as f64 && b.trunc() <= i32::MAX as f64;
+                assert_eq!(flags.invalid, !in_range);
+                assert_eq!(flags.inexact, in_range && b.trunc() != b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_saturation() {
+        let rne = RoundingMode::NearestEven;
+        let x86 = FpEnv::profile(Profile::X86Sse);
+        let riscv = FpEnv::profile(Profile::RiscV);
+        let arm = FpEnv::profile(Profile::ArmAarch64);
+        for (a, signed, env, expected, flags) in [
+            // in range
+            (2.5f32, true, riscv, 2i128, 0b00001),
+            (-2.5, true, x86, -2, 0b00001),
+            (-0.4, false, riscv, 0, 0b00001),
+            (-0.0, false, arm, 0, 0b00000),
+            (2147483520.0, true, x86, 2147483520, 0b00000),
+            // overflow
+            (2147483648.0, true, x86, i32::MIN as i128, 0b10000),
+            (2147483648.0, true, riscv, i32::MAX as i128, 0b10000),
+            (2147483648.0, true, arm, i32::MAX as i128, 0b10000),
+            (-3e9, true, riscv, i32::MIN as i128, 0b10000),
+            (f32::NEG_INFINITY, true, arm, i32::MIN as i128, 0b10000),
+            (5e9, false, x86, u32::MAX as i128, 0b10000),
+            (5e9, false, riscv, u32::MAX as i128, 0b10000),
+            // negative to unsigned
+            (-0.6, false, riscv, 0, 0b10000),
+            (-1.0, false, x86, u32::MAX as i128, 0b10000),
+            (-1.0, false, arm, 0, 0b10000),
+            // nan
+            (f32::NAN, true, x86, i32::MIN as i128, 0b10000),
+            (f32::NAN, true, riscv, i32::MAX as i128, 0b10000),
+            (f32::NAN, true, arm, 0, 0b10000),
+            (-f32::NAN, false, riscv, u32::MAX as i128, 0b10000),
+            (f32::NAN, false, x86, u32::MAX as i128, 0b10000),
+            (f32::NAN, false, arm, 0, 0b10000),
+        ] {
+            let (res, res_flags) = float_to_int(a, signed, 32, rne, &env);
+            assert_eq!(res, BigInt::from(expected), "{} {:?}", a, env.saturation);
+            assert_eq!(res_flags.to_bits(), flags, "{} {:?}", a, env.saturation);
+        }
+
+        // rounding modes
+        for (rm, expected) in [
+            (RoundingMode::NearestEven, [2, 2, -2, -2]),
+            (RoundingMode::NearestMaxMag, [3, 2, -3, -2]),
+            (RoundingMode::TowardZero, [2, 1, -2, -1]),
+            (RoundingMode::TowardPositive, [3, 2, -2, -1]),
+            (RoundingMode::TowardNegative, [2, 1, -3, -2]),
+        ] {
+            for (a, expected) in [2.5f64, 1.5, -2.5, -1.5].into_iter().zip(expected) {
+                let (res, _) = float_to_int(a, true, 8, rm, &FpEnv::default());
+                assert_eq!(res, BigInt::from(expected), "{} {:?}", a, rm);
+            }
+        }
+
+        assert_eq!(int_to_bits(&BigInt::from(-1), 8), BigUint::from(0xffu32));
+        assert_eq!(int_to_bits(&BigInt::from(-128), 8), BigUint::from(0x80u32));
+        assert_eq!(int_to_bits(&BigInt::from(5), 64), BigUint::from(5u32));
+    }
+}
@@ -0,0 +1,157 @@
+use crate::{
+    decode, extract, flush_input, propagate_nan, round_at, round_pack, ExceptionFlags, FloatType,
+    FpEnv, RoundingMode,
+};
+
+// IEEE roundToIntegral with the rounding direction of rm,
+// roundToIntegralTiesToEven is NearestEven and so on
+// only a signaling nan raises invalid
+pub fn round_to_integral<T: FloatType>(a: T, rm: RoundingMode, env: &FpEnv) -> (T, ExceptionFlags) {
+    let (res, mut flags) = round_to_integral_exact(a, rm, env);
+    flags.inexact = false;
+    (res, flags)
+}
+
+// IEEE roundToIntegralExact, which also raises inexact
+pub fn round_to_integral_exact<T: FloatType>(
+    a: T,
+    rm: RoundingMode,
+    env: &FpEnv,
+) -> (T, ExceptionFlags) {
+    let mut flags = ExceptionFlags::default();
+    let a = flush_input(a, env, &mut flags);
+    let num_a = a.to_biguint();
+    let (_, exp, man) = extract::<T>(&num_a);
+    if exp == T::max_exp() {
+        if man.bits() != 0 {
+            flags.invalid = !man.bit(T::SIG as u64 - 2);
+            return (propagate_nan(env.nan_policy, &[a]).unwrap(), flags);
+        }
+        return (a, flags);
+    }
+
+    let (sign, exp, sig) = decode::<T>(&num_a);
+    if exp >= 0 {
+        // already integral
+        return (a, flags);
+    }
+    let (int, inexact) = round_at(sign, &sig, exp, 0, rm);
+    flags.inexact = inexact;
+    // a zero result keeps the sign of a
+    let (bits, _) = round_pack::<T>(sign, 0, &int, rm, Default::default());
+    (T::from_biguint(&bits), flags)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{round_to_integral, round_to_integral_exact, FpEnv, RoundingMode};
+    use half::f16;
+
+    fn native(a: f64, rm: RoundingMode) -> f64 {
+        match rm {
+            RoundingMode::NearestEven => a.round_ties_even(),
+            RoundingMode::NearestMaxMag => a.round(),
+            RoundingMode::TowardZero => a.trunc(),
+            RoundingMode::TowardPositive => a.ceil(),
+            RoundingMode::TowardNegative => a.floor(),
+            RoundingMode::Stochastic(_) => unreachable!("not in MODES"),
+        }
+    }
+
+    const MODES: [RoundingMode; 5] = [
+        RoundingMode::NearestEven,
+        RoundingMode::NearestMaxMag,
+        RoundingMode::TowardZero,
+        RoundingMode::TowardPositive,
+        RoundingMode::TowardNegative,
+    ];
+
+    #[test]
+    fn test() {
+        let env = FpEnv::default();
+        let mut state = 0x2545f4914f6cdd1du64;
+        for _ in 0..10000 {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let a = f64::from_bits(state);
+            // exponents around 2^0 to 2^53
+            let b =
+                f64::from_bits((state & 0x800fffffffffffff) | ((1000 + (state >> 52) % 80) << 52));
+            for a in [a, b] {
+                if a.is_nan() {
+                    continue;
+                }
+                for rm in MODES {
+                    let expected = native(a, rm);
+                    let (res, flags) = round_to_integral_exact(a, rm, &env);
+                    assert_eq!(res.to_bits(), expected.to_bits(), "{} {:?}", a, rm);
+                    assert_eq!(flags.inexact, expected != a);
+                    assert!(!flags.invalid);
+                    let (res, flags) = round_to_integral(a as f32, rm, &env);
+                    assert_eq!(
+                        res.to_bits(),
+                        (native(a as f32 as f64, rm) as f32).to_bits()
+                    );
+                    assert!(!flags.inexact);
+                }
+            }
+        }
+
+        // f16 is exact in f64
+        for num in 0..=u16::MAX {
+            let a = f16::from_bits(num);
+            if a.is_nan() {
+                continue;
+            }
+            for rm in MODES {
+                let (res, _) = round_to_integral(a, rm, &env);
+                let expected = f16::from_f64(native(a.to_f64(), rm));
+                assert_eq!(res.to_bits(), expected.to_bits(), "{} {:?}", a, rm);
+            }
+        }
+    }
+
+    #[test]
+    fn test_special() {
+        let env = FpEnv::default();
+        let rne = RoundingMode::NearestEven;
+        for (a, rm, expected, flags) in [
+            (-0.0f32, rne, -0.0f32, 0b00000),
+            (-0.4, rne, -0.0, 0b00001),
+            (-0.4, RoundingMode::TowardPositive, -0.0, 0b00001),
+            (0.4, RoundingMode::TowardNegative, 0.0, 0b00001),
+            (
+                f32::from_bits(1),
+                RoundingMode::TowardPositive,
+                1.0,
+                0b00001,
+            ),
+            (
+                -f32::from_bits(1),
+                RoundingMode::TowardNegative,
+                -1.0,
+                0b00001,
+            ),
+            (0.5, rne, 0.0, 0b00001),
+            (0.5, RoundingMode::NearestMaxMag, 1.0, 0b00001),
+            (8388609.0, rne, 8388609.0, 0b00000),
+            (f32::MAX, rne, f32::MAX, 0b00000),
+            (f32::NEG_INFINITY, rne, f32::NEG_INFINITY, 0b00000),
+        ] {
+            let (res, res_flags) = round_to_integral_exact(a, rm, &env);
+            assert_eq!(res.to_bits(), expected.to_bits(), "{} {:?}", a, rm);
+            assert_eq!(res_flags.to_bits(), flags, "{} {:?}", a, rm);
+        }
+
+        // signaling nan raises invalid in both
+        let snan = f32::from_bits(0xff800123);
+        let (res, flags) = round_to_integral(snan, rne, &env);
+        assert_eq!((res.to_bits(), flags.to_bits()), (0xffc00123, 0b10000));
+        let (res, flags) = round_to_integral_exact(f32::NAN, rne, &env);
+        assert_eq!(
+            (res.to_bits(), flags.to_bits()),
+            (f32::NAN.to_bits(), 0b00000)
+        );
+    }
+}
@@ -1,18 +1,62 @@
-use half::f16;
+use half::{bf16, f16};
 use num_bigint::{BigUint, ToBigUint};
 use std::fmt::Display;
 
 mod add;
+mod arith;
+mod bigfloat;
 mod classify;
+mod compare;
+mod convert;
+mod decimal;
+mod elementary;
+mod env;
+mod flopoco;
+mod hardfloat;
+mod hexfloat;
+mod info;
+mod int;
+mod integral;
+mod nan;
+mod neighbor;
+mod rational;
+mod raw;
+mod remainder;
+mod round;
+mod scale;
+mod sign;
+mod soft;
 
 pub use add::*;
+pub use arith::*;
+pub use bigfloat::*;
 pub use classify::*;
+pub use compare::*;
+pub use convert::*;
+pub use decimal::*;
+pub use elementary::*;
+pub use env::*;
+pub use flopoco::*;
+pub use hardfloat::*;
+pub use hexfloat::*;
+pub use info::*;
+pub use int::*;
+pub use integral::*;
+pub use nan::*;
+pub use neighbor::*;
+pub use rational::*;
+pub use raw::*;
+pub use remainder::*;
+pub use round::*;
+pub use scale::*;
+pub use sign::*;
+pub use soft::*;
 
 pub trait FloatType: Display + Copy + Clone {
     const EXP: usize;
     const SIG: usize;
     const WIDTH: usize = Self::EXP + Self::SIG;
-    const NAME: &'static str;
+    fn name() -> String;
     fn to_biguint(self) -> BigUint;
     fn from_biguint(num: &BigUint) -> Self;
     fn bias() -> BigUint {
@@ -26,7 +70,9 @@ pub trait FloatType: Display + Copy + Clone {
 impl FloatType for f16 {
     const EXP: usize = 5;
     const SIG: usize = 11;
-    const NAME: &'static str = "f16";
+    fn name() -> String {
+        "f16".to_string()
+    }
     fn to_biguint(self) -> BigUint {
         self.to_bits().to_biguint().unwrap()
     }
@@ -38,7 +84,9 @@ impl FloatType for f16 {
 impl FloatType for f32 {
     const EXP: usize = 8;
     const SIG: usize = 24;
-    const NAME: &'static str = "f32";
+    fn name() -> String {
+        "f32".to_string()
+    }
     fn to_biguint(self) -> BigUint {
         self.to_bits().to_biguint().unwrap()
     }
@@ -50,7 +98,9 @@ impl FloatType for f32 {
 impl FloatType for f64 {
     const EXP: usize = 11;
     const SIG: usize = 53;
-    const NAME: &'static str = "f64";
+    fn name() -> String {
+        "f64".to_string()
+    }
     fn to_biguint(self) -> BigUint {
         self.to_bits().to_biguint().unwrap()
     }
@@ -59,6 +109,62 @@ impl FloatType for f64 {
     }
 }
 
+impl FloatType for bf16 {
+    const EXP: usize = 8;
+    const SIG: usize = 8;
+    fn name() -> String {
+        "bf16".to_string()
+    }
+    fn to_biguint(self) -> BigUint {
+        self.to_bits().to_biguint().unwrap()
+    }
+    fn from_biguint(num: &BigUint) -> Self {
+        bf16::from_bits(num.iter_u32_digits().next().unwrap_or(0) as u16)
+    }
+}
+
+// IEEE-style format with EXP exponent bits and SIG significand bits
+// including the implicit bit, at most 128 bits wide
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CustomFloat<const EXP: usize, const SIG: usize>(pub u128);
+
+// widest exponent field of CustomFloat, binary256 has 19
+// operations hold exact values as big integers of up to about 2^EXP bits
+pub const CUSTOM_MAX_EXP: usize = 20;
+
+impl<const EXP: usize, const SIG: usize> CustomFloat<EXP, SIG> {
+    // fails to compile for unsupported widths when the format is used
+    const VALID: () = assert!(
+        EXP >= 2 && EXP <= CUSTOM_MAX_EXP && SIG >= 2 && EXP + SIG <= 128,
+        "CustomFloat needs 2 <= EXP <= CUSTOM_MAX_EXP, SIG >= 2 and EXP + SIG <= 128"
+    );
+}
+
+impl<const EXP: usize, const SIG: usize> FloatType for CustomFloat<EXP, SIG> {
+    const EXP: usize = EXP;
+    const SIG: usize = SIG;
+    fn name() -> String {
+        let () = Self::VALID;
+        format!("custom<{},{}>", EXP, SIG)
+    }
+    fn to_biguint(self) -> BigUint {
+        let () = Self::VALID;
+        self.0.to_biguint().unwrap()
+    }
+    fn from_biguint(num: &BigUint) -> Self {
+        let () = Self::VALID;
+        let bits = num & ((1.to_biguint().unwrap() << (EXP + SIG)) - 1u32);
+        CustomFloat(u128::try_from(&bits).unwrap())
+    }
+}
+
+impl<const EXP: usize, const SIG: usize> Display for CustomFloat<EXP, SIG> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // shortest decimal in this format
+        f.write_str(&print_shortest_decimal::<Self>(&self.to_biguint()))
+    }
+}
+
 pub fn range<T: FloatType>(num: &BigUint, upper: usize, lower: usize) -> BigUint {
     assert!(upper >= lower);
     (num >> lower) & ((1.to_biguint().unwrap() << (upper - lower + 1)) - 1u32)
@@ -77,6 +183,26 @@ pub fn extract<T: FloatType>(num: &BigUint) -> (BigUint, BigUint, BigUint) {
     )
 }
 
+// finite number as (-1)^sign * sig * 2^exp
+pub(crate) fn decode<T: FloatType>(num: &BigUint) -> (bool, i64, BigUint) {
+    let zero = 0.to_biguint().unwrap();
+    let (sign, exp, man) = extract::<T>(num);
+    let bias = (1i64 << (T::EXP - 1)) - 1;
+    let lsb = 1 - bias - (T::SIG as i64 - 1);
+    if exp == zero {
+        // subnormal/zero
+        (sign != zero, lsb, man)
+    } else {
+        // add implicit 1.0
+        let exp = exp.iter_u64_digits().next().unwrap_or(0) as i64;
+        (
+            sign != zero,
+            lsb + exp - 1,
+            man | (1.to_biguint().unwrap() << (T::SIG - 1)),
+        )
+    }
+}
+
 pub fn pack<T: FloatType>(sign: &BigUint, exp: &BigUint, man: &BigUint) -> BigUint {
     // validate
     let one = 1.to_biguint().unwrap();
@@ -96,102 +222,3 @@ pub fn print_float<T: FloatType>(bits: &BigUint) -> String {
         width = T::SIG - 1
     )
 }
-
-pub fn to_hardfloat<T: FloatType>(num: &BigUint) -> BigUint {
-    let f0: BigUint = 0.to_biguint().unwrap();
-    // http://www.jhauser.us/arithmetic/HardFloat-1/doc/HardFloat-Verilog.html
-    // recFNFromFN
-    // float32: 1+8+23
-    // hardfloat32: 1+9+23
-    // EXP=8, SIG=24
-    // k=EXP-1=7
-    let sign = bit::<T>(num, T::EXP + T::SIG - 1);
-    let exp_in = range::<T>(num, T::EXP + T::SIG - 2, T::SIG - 1);
-    let sig_in = range::<T>(num, T::SIG - 2, 0);
-
-    let is_zero_exp_in = exp_in == f0;
-    let is_zero_sig_in = sig_in == f0;
-
-    let k = T::EXP - 1;
-    let pow2k = (1 << k).to_biguint().unwrap();
-    let (exp, sig) = if is_zero_exp_in && is_zero_sig_in {
-        // zero
-        (f0.clone(), f0.clone())
-    } else if is_zero_exp_in && !is_zero_sig_in {
-        // subnormal
-        let mut leading_zeros = 0u32;
-        for bit in (0..T::SIG - 1).rev() {
-            if sig_in.bit(bit as u64) {
-                break;
-            } else {
-                leading_zeros += 1;
-            }
-        }
-        let n = leading_zeros;
-        let exp = pow2k + 2u32 - n;
-        let sig = sig_in << n;
-        (exp, sig)
-    } else if exp_in == ((1 << (T::EXP + 1)) - 1).to_biguint().unwrap() {
-        // special
-        if is_zero_sig_in {
-            // infinity
-            (0b110.to_biguint().unwrap() << (T::EXP - 3), f0)
-        } else {
-            // NaN
-            (0b111.to_biguint().unwrap() << (T::EXP - 3), f0)
-        }
-    } else {
-        // normal
-        let exp = exp_in + pow2k + 1u32;
-        (exp, sig_in)
-    };
-    (sign << (T::EXP + T::SIG)) | (exp << (T::SIG - 1)) | sig
-}
-
-pub fn to_flopoco<T: FloatType>(num: &BigUint) -> BigUint {
-    let f0: BigUint = 0.to_biguint().unwrap();
-    // two exn bits at the msb: 0=zero, 1=normal, 2=inf, 3=nan
-    // no subnormal numbers
-    let sign = bit::<T>(num, T::EXP + T::SIG - 1);
-    let exp_in = range::<T>(num, T::EXP + T::SIG - 2, T::SIG - 1);
-    let sig_in = range::<T>(num, T::SIG - 2, 0);
-
-    let is_zero_exp_in = exp_in == f0;
-    let is_zero_sig_in = sig_in == f0;
-
-    let (exn, exp, sig) = if is_zero_exp_in && is_zero_sig_in {
-        // zero
-        (f0.clone(), f0.clone(), f0.clone())
-    } else if is_zero_exp_in && !is_zero_sig_in {
-        // subnormal
-        todo!()
-    } else if exp_in == ((1 << (T::EXP + 1)) - 1).to_biguint().unwrap() {
-        // special
-        if is_zero_sig_in {
-            // infinity
-            (2.to_biguint().unwrap(), f0.clone(), f0)
-        } else {
-            // NaN
-            (3.to_biguint().unwrap(), f0.clone(), f0)
-        }
-    } else {
-        // normal
-        (1.to_biguint().unwrap(), exp_in, sig_in)
-    };
-    (exn << (T::EXP + T::SIG)) | (sign << (T::EXP + T::SIG - 1)) | (exp << (T::SIG - 1)) | sig
-}
-
-pub fn print_hardfloat<T: FloatType>(bits: &BigUint) -> String {
-    let sign = bit::<T>(bits, T::SIG + T::EXP);
-    let exp = range::<T>(bits, T::SIG + T::EXP - 1, T::SIG - 1);
-    let sig = range::<T>(bits, T::SIG - 2, 0);
-    format!("sign={},exp={},sig={}", sign, exp, sig)
-}
-
-pub fn print_flopoco<T: FloatType>(bits: &BigUint) -> String {
-    let exn = range::<T>(bits, T::SIG + T::EXP + 1, T::SIG + T::EXP);
-    let sign = bit::<T>(bits, T::SIG + T::EXP - 1);
-    let exp = range::<T>(bits, T::SIG + T::EXP - 2, T::SIG - 1);
-    let sig = range::<T>(bits, T::SIG - 2, 0);
-    format!("exn={},sign={},exp={},sig={}", exn, sign, exp, sig)
-}
@@ -0,0 +1,181 @@
+use num_bigint::{BigUint, ToBigUint};
+
+use crate::{extract, FloatType};
+
+// which nan an operation returns
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NanPolicy {
+    // x86 SSE/AVX: the first nan operand, quieted
+    // invalid operations return the negative default nan ("QNaN floating-point indefinite")
+    #[default]
+    X86,
+    // ARM with FPCR.DN=0: the first signaling nan, otherwise the first quiet nan, quieted
+    // invalid operations return the positive default nan
+    Arm,
+    // RISC-V, and ARM with FPCR.DN=1: always the positive canonical nan
+    Canonical,
+    // CUDA: always the positive nan with every fraction bit set, 0x7fffffff for f32
+    Cuda,
+}
+
+// (sign, quiet, payload) of a nan, the payload excludes the quiet bit
+pub fn decode_nan<T: FloatType>(a: T) -> Option<(bool, bool, BigUint)> {
+    let num_a = a.to_biguint();
+    let (sign, exp, man) = extract::<T>(&num_a);
+    if exp != T::max_exp() || man.bits() == 0 {
+        return None;
+    }
+    let quiet = man.bit(T::SIG as u64 - 2);
+    let payload = man & ((1.to_biguint().unwrap() << (T::SIG - 2)) - 1u32);
+    Some((sign.bits() != 0, quiet, payload))
+}
+
+// nan with the given payload, None if the payload does not fit
+// or if a signaling nan would have a zero payload, which encodes inf
+pub fn encode_nan<T: FloatType>(sign: bool, quiet: bool, payload: &BigUint) -> Option<T> {
+    if payload.bits() as usize > T::SIG - 2 || (!quiet && payload.bits() == 0) {
+        return None;
+    }
+    let bits = ((sign as u32).to_biguint().unwrap() << (T::WIDTH - 1))
+        | (T::max_exp() << (T::SIG - 1))
+        | ((quiet as u32).to_biguint().unwrap() << (T::SIG - 2))
+        | payload;
+    Some(T::from_biguint(&bits))
+}
+
+// quiet nan with a zero payload
+pub fn default_nan<T: FloatType>(sign: bool) -> T {
+    encode_nan::<T>(sign, true, &BigUint::default()).unwrap()
+}
+
+// set the quiet bit of a nan
+pub fn quiet_nan<T: FloatType>(a: T) -> T {
+    T::from_biguint(&(a.to_biguint() | (1.to_biguint().unwrap() << (T::SIG - 2))))
+}
+
+// the only nan a policy produces, None if nans propagate
+pub(crate) fn fixed_nan<T: FloatType>(policy: NanPolicy) -> Option<T> {
+    match policy {
+        NanPolicy::X86 | NanPolicy::Arm => None,
+        NanPolicy::Canonical => Some(default_nan(false)),
+        NanPolicy::Cuda => {
+            let payload = (1.to_biguint().unwrap() << (T::SIG - 2)) - 1u32;
+            encode_nan(false, true, &payload)
+        }
+    }
+}
+
+// nan result of an operation on these operands, None if none of them is a nan
+pub(crate) fn propagate_nan<T: FloatType>(policy: NanPolicy, operands: &[T]) -> Option<T> {
+    let nans: Vec<(T, bool)> = operands
+        .iter()
+        .filter_map(|&a| decode_nan(a).map(|(_, quiet, _)| (a, quiet)))
+        .collect();
+    let (first, _) = *nans.first()?;
+    Some(match policy {
+        NanPolicy::X86 => quiet_nan(first),
+        NanPolicy::Arm => {
+            let signaling = nans.iter().find(|(_, quiet)| !quiet);
+            quiet_nan(signaling.map_or(first, |&(a, _)| a))
+        }
+        NanPolicy::Canonical | NanPolicy::Cuda => fixed_nan(policy).unwrap(),
+    })
+}
+
+// nan result of an invalid operation like inf - inf
+pub(crate) fn invalid_nan<T: FloatType>(policy: NanPolicy) -> T {
+    fixed_nan(policy).unwrap_or_else(|| default_nan(policy == NanPolicy::X86))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        decode_nan, default_nan, encode_nan, maximum_number, minimum, round_to_integral,
+        softfloat_add, softfloat_convert, softfloat_sub, FpEnv, NanPolicy, RoundingMode,
+    };
+    use half::f16;
+    use num_bigint::BigUint;
+
+    #[test]
+    fn test_payload() {
+        assert_eq!(decode_nan(1.0f32), None);
+        assert_eq!(decode_nan(f32::INFINITY), None);
+        assert_eq!(
+            decode_nan(f32::from_bits(0xffc00123)),
+            Some((true, true, BigUint::from(0x123u32)))
+        );
+        assert_eq!(
+            decode_nan(f32::from_bits(0x7f800001)),
+            Some((false, false, BigUint::from(1u32)))
+        );
+        let res: f32 = encode_nan(true, false, &BigUint::from(0x123u32)).unwrap();
+        assert_eq!(res.to_bits(), 0xff800123);
+        assert_eq!(encode_nan::<f32>(false, false, &BigUint::default()), None);
+        assert_eq!(
+            encode_nan::<f32>(false, true, &BigUint::from(1u32 << 22)),
+            None
+        );
+        assert_eq!(default_nan::<f16>(false).to_bits(), 0x7e00);
+        assert_eq!(default_nan::<f64>(true).to_bits(), 0xfff8000000000000);
+
+        // round trip of every f16 nan
+        for num in 0..=u16::MAX {
+            let a = f16::from_bits(num);
+            if let Some((sign, quiet, payload)) = decode_nan(a) {
+                let res: f16 = encode_nan(sign, quiet, &payload).unwrap();
+                assert_eq!(res.to_bits(), num);
+            } else {
+                assert!(!a.is_nan());
+            }
+        }
+    }
+
+    #[test]
+    fn test_policy() {
+        let qnan_a = f32::from_bits(0x7fc00001);
+        let snan_b = f32::from_bits(0xff800002);
+        let rne = RoundingMode::NearestEven;
+        for (policy, first, signaling, invalid) in [
+            (NanPolicy::X86, 0x7fc00001u32, 0xffc00002u32, 0xffc00000u32),
+            (NanPolicy::Arm, 0x7fc00001, 0xffc00002, 0x7fc00000),
+            (NanPolicy::Canonical, 0x7fc00000, 0x7fc00000, 0x7fc00000),
+            (NanPolicy::Cuda, 0x7fffffff, 0x7fffffff, 0x7fffffff),
+        ] {
+            let env = FpEnv {
+                nan_policy: policy,
+                ..Default::default()
+            };
+            // x86 takes the first operand, ARM prefers the signaling one
+            let x86_or_arm = |x86: u32, arm: u32| match policy {
+                NanPolicy::Arm => arm,
+                _ => x86,
+            };
+            let res = softfloat_add(qnan_a, snan_b, &env).0;
+            assert_eq!(res.to_bits(), x86_or_arm(first, signaling), "{:?}", policy);
+            let res = softfloat_add(snan_b, qnan_a, &env).0;
+            assert_eq!(res.to_bits(), signaling, "{:?}", policy);
+            let res = softfloat_sub(1.0, qnan_a, &env).0;
+            assert_eq!(res.to_bits(), first, "{:?}", policy);
+            let res = softfloat_sub(f32::INFINITY, f32::INFINITY, &env).0;
+            assert_eq!(res.to_bits(), invalid, "{:?}", policy);
+            let res = softfloat_add(f32::NEG_INFINITY, f32::INFINITY, &env).0;
+            assert_eq!(res.to_bits(), invalid, "{:?}", policy);
+
+            let (res, _) = minimum(qnan_a, snan_b, &env);
+            assert_eq!(res.to_bits(), x86_or_arm(first, signaling), "{:?}", policy);
+            let (res, _) = maximum_number(qnan_a, snan_b, &env);
+            assert_eq!(res.to_bits(), x86_or_arm(first, signaling), "{:?}", policy);
+            let (res, _) = round_to_integral(snan_b, rne, &env);
+            assert_eq!(res.to_bits(), signaling, "{:?}", policy);
+
+            // payloads keep their msb when converting
+            let (res, _) = softfloat_convert::<f32, f64>(snan_b, &env);
+            let expected = match policy {
+                NanPolicy::Canonical => 0x7ff8000000000000,
+                NanPolicy::Cuda => 0x7fffffffffffffff,
+                _ => 0xfff8000040000000,
+            };
+            assert_eq!(res.to_bits(), expected, "{:?}", policy);
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use num_bigint::{BigInt, BigUint, Sign, ToBigUint};
+
+use crate::{extract, pack, FloatType};
+
+// IEEE nextUp: the smallest number greater than a
+// nan is quieted, +inf stays and -inf goes to the most negative finite number
+pub fn next_up<T: FloatType>(a: T) -> T {
+    let zero = 0.to_biguint().unwrap();
+    let one = 1.to_biguint().unwrap();
+    let num_a = a.to_biguint();
+    let (sign, exp, man) = extract::<T>(&num_a);
+    let man_mask = (&one << (T::SIG - 1)) - &one;
+
+    let (sign, exp, man) = if exp == T::max_exp() {
+        if man != zero {
+            // quiet nan
+            (sign, exp, man | (&one << (T::SIG - 2)))
+        } else if sign == zero {
+            (sign, exp, man)
+        } else {
+            // largest finite number
+            (sign, T::max_exp() - &one, man_mask)
+        }
+    } else if exp == zero && man == zero {
+        // smallest subnormal number, for both +0 and -0
+        (zero.clone(), zero, one)
+    } else if sign == zero {
+        // increase magnitude, carrying into the exponent
+        if man == man_mask {
+            (sign, exp + &one, zero)
+        } else {
+            (sign, exp, man + &one)
+        }
+    } else if man == zero {
+        // decrease magnitude, borrowing from the exponent
+        (sign, exp - &one, man_mask)
+    } else {
+        (sign, exp, man - &one)
+    };
+    T::from_biguint(&pack::<T>(&sign, &exp, &man))
+}
+
+// IEEE nextDown: the largest number less than a
+pub fn next_down<T: FloatType>(a: T) -> T {
+    let one = 1.to_biguint().unwrap();
+    let flip = |num: T| T::from_biguint(&(num.to_biguint() ^ (&one << (T::WIDTH - 1))));
+    flip(next_up(flip(a)))
+}
+
+// weight of the lsb of a, which is the gap to the next larger
+// magnitude number, positive and at least the smallest subnormal number
+pub fn ulp<T: FloatType>(a: T) -> T {
+    let zero = 0.to_biguint().unwrap();
+    let one = 1.to_biguint().unwrap();
+    let num_a = a.to_biguint();
+    let (_sign, exp, man) = extract::<T>(&num_a);
+    if exp == T::max_exp() {
+        // inf and nan
+        return T::from_biguint(&pack::<T>(&zero, &exp, &man));
+    }
+
+    let exp = exp.iter_u64_digits().next().unwrap_or(0).max(1);
+    let shift = (T::SIG - 1) as u64;
+    let (exp, man) = if exp > shift {
+        ((exp - shift).to_biguint().unwrap(), zero.clone())
+    } else {
+        // the lsb of a subnormal number or of a small normal number
+        (zero.clone(), one << (exp - 1))
+    };
+    T::from_biguint(&pack::<T>(&zero, &exp, &man))
+}
+
+// position of a on the number line counting encodings, +0 and -0 are both 0
+fn ordinal<T: FloatType>(a: T) -> BigInt {
+    let num_a = a.to_biguint();
+    let (sign, _, _) = extract::<T>(&num_a);
+    let magnitude = num_a & ((1.to_biguint().unwrap() << (T::WIDTH - 1)) - 1u32);
+    let sign = if sign.bits() != 0 {
+        Sign::Minus
+    } else {
+        Sign::Plus
+    };
+    BigInt::from_biguint(sign, magnitude)
+}
+
+// number of steps of next_up from the smaller to the larger of a and b,
+// None if either is nan
+pub fn ulp_distance<T: FloatType>(a: T, b: T) -> Option<BigUint> {
+    let zero = 0.to_biguint().unwrap();
+    for num in [a, b] {
+        let (_, exp, man) = extract::<T>(&num.to_biguint());
+        if exp == T::max_exp() && man != zero {
+            return None;
+        }
+    }
+    Some((ordinal(a) - ordinal(b)).magnitude().clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{next_down, next_up, ulp, ulp_distance, CustomFloat};
+    use half::f16;
+    use num_bigint::{BigUint, ToBigUint};
+
+    #[test]
+    fn test() {
+        let min = f32::from_bits(1);
+        for (a, up, down, ulp_a) in [
+            (
+                1.0f32,
+                1.0 + f32::EPSILON,
+                1.0 - f32::EPSILON / 2.0,
+                f32::EPSILON,
+            ),
+            (
+                -1.0,
+                -1.0 + f32::EPSILON / 2.0,
+                -1.0 - f32::EPSILON,
+                f32::EPSILON,
+            ),
+            (0.0, min, -min, min),
+            (-0.0, min, -min, min),
+            (min, 2.0 * min, 0.0, min),
+            (-min, -0.0, -2.0 * min, min),
+            (
+                f32::MIN_POSITIVE,
+                f32::from_bits(0x00800001),
+                f32::from_bits(0x007fffff),
+                min,
+            ),
+            (
+                f32::from_bits(0x007fffff),
+                f32::MIN_POSITIVE,
+                f32::from_bits(0x007ffffe),
+                min,
+            ),
+            (
+                f32::MAX,
+                f32::INFINITY,
+                f32::from_bits(0x7f7ffffe),
+                2f32.powi(104),
+            ),
+            (f32::INFINITY, f32::INFINITY, f32::MAX, f32::INFINITY),
+            (
+                f32::NEG_INFINITY,
+                -f32::MAX,
+                f32::NEG_INFINITY,
+                f32::INFINITY,
+            ),
+            // 2^-110 whose lsb is a subnormal number
+            (
+                f32::from_bits(0x08800000),
+                f32::from_bits(0x08800001),
+                f32::from_bits(0x087fffff),
+                f32::from_bits(1 << 16),
+            ),
+        ] {
+            assert_eq!(next_up(a).to_bits(), up.to_bits(), "{}", a);
+            assert_eq!(next_down(a).to_bits(), down.to_bits(), "{}", a);
+            assert_eq!(ulp(a).to_bits(), ulp_a.to_bits(), "{}", a);
+        }
+        assert!(next_up(f32::NAN).is_nan());
+        assert!(ulp(f32::NAN).is_nan());
+        // signaling nan is quieted
+        assert_eq!(next_up(f32::from_bits(0x7f800001)).to_bits(), 0x7fc00001);
+
+        assert_eq!(ulp_distance(1.0f32, 1.0), Some(0u32.to_biguint().unwrap()));
+        assert_eq!(ulp_distance(0.0f32, -0.0), Some(0u32.to_biguint().unwrap()));
+        assert_eq!(ulp_distance(min, -min), Some(2u32.to_biguint().unwrap()));
+        assert_eq!(
+            ulp_distance(1.0f32, 2.0),
+            Some((1u32 << 23).to_biguint().unwrap())
+        );
+        assert_eq!(
+            ulp_distance(f32::NEG_INFINITY, f32::INFINITY),
+            Some(BigUint::from(0xff000000u32))
+        );
+        assert_eq!(ulp_distance(f32::NAN, 1.0), None);
+
+        // against the native implementation
+        let mut state = 0x2545f4914f6cdd1du64;
+        for _ in 0..20000 {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let a = f64::from_bits(state);
+            let b = f32::from_bits((state >> 32) as u32);
+            if a.is_nan() || b.is_nan() {
+                continue;
+            }
+            assert_eq!(next_up(a).to_bits(), a.next_up().to_bits());
+            assert_eq!(next_down(a).to_bits(), a.next_down().to_bits());
+            assert_eq!(next_up(b).to_bits(), b.next_up().to_bits());
+            assert_eq!(next_down(b).to_bits(), b.next_down().to_bits());
+            let custom = CustomFloat::<8, 24>(b.to_bits() as u128);
+            assert_eq!(next_up(custom).0 as u32, b.next_up().to_bits());
+            if b.is_finite() && b.abs() < f32::MAX {
+                assert_eq!(ulp(b), b.abs().next_up() - b.abs());
+            }
+        }
+
+        // every f16 step
+        for num in 0..=u16::MAX {
+            let a = f16::from_bits(num);
+            if a.is_nan() || a == f16::INFINITY {
+                continue;
+            }
+            let up = next_up(a);
+            assert!(up > a);
+            assert_eq!(
+                ulp_distance(a, up),
+                Some(1u32.to_biguint().unwrap()),
+                "{}",
+                a
+            );
+            if up != f16::INFINITY && a != f16::NEG_INFINITY {
+                // next_down goes back to +0 from both zeros
+                let back = if a.to_bits() == 0x8000 { f16::ZERO } else { a };
+                assert_eq!(next_down(up).to_bits(), back.to_bits());
+            }
+        }
+    }
+}
@@ -0,0 +1,280 @@
+use num_bigint::{BigInt, BigUint, Sign, ToBigUint};
+
+use crate::{decode, extract, round_env, ExceptionFlags, FloatType, FpEnv, RoundingMode};
+
+// exact value of a finite encoding as (mantissa, exp)
+// with value = mantissa * 2^exp and an odd mantissa unless it is zero
+// returns None for inf and nan
+pub fn to_rational<T: FloatType>(bits: &BigUint) -> Option<(BigInt, i64)> {
+    let (_, exp, _) = extract::<T>(bits);
+    if exp == T::max_exp() {
+        return None;
+    }
+    let (sign, exp, sig) = decode::<T>(bits);
+    if sig.bits() == 0 {
+        return Some((BigInt::default(), 0));
+    }
+    let zeros = sig.trailing_zeros().unwrap_or(0);
+    let sign = if sign { Sign::Minus } else { Sign::Plus };
+    Some((BigInt::from_biguint(sign, sig >> zeros), exp + zeros as i64))
+}
+
+// round num / den to the format T in the rounding mode of env, with FTZ
+pub fn round_rational<T: FloatType>(
+    num: &BigInt,
+    den: &BigUint,
+    env: &FpEnv,
+) -> (T, ExceptionFlags) {
+    let one = 1.to_biguint().unwrap();
+    let sign = num.sign() == Sign::Minus;
+    let num = num.magnitude();
+    // divide with at least two bits beyond the precision, or 64 for
+    // stochastic rounding which reads that many of the discarded fraction,
+    // and keep the remainder in the sticky lsb
+    let extra = match env.rounding {
+        RoundingMode::Stochastic(_) => 66,
+        _ => 3,
+    };
+    let shift = (T::SIG as i64 + extra + den.bits() as i64 - num.bits() as i64).max(0) as usize;
+    let num = num << shift;
+    let mut quotient = &num / den;
+    if &quotient * den != num {
+        quotient |= one;
+    }
+    round_env::<T>(sign, -(shift as i64), &quotient, env)
+}
+
+// error of an encoding against the exact value it was converted from
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ConversionError {
+    pub exact: bool,
+    // |encoded - exact| as (mantissa, exp) with value mantissa * 10^exp
+    // and the mantissa in [1, 10), or (0.0, 0), so that it does not underflow
+    pub abs_error: (f64, i64),
+    // |encoded - exact| / |exact|
+    pub rel_error: f64,
+    // |encoded - exact| / ulp of the encoding
+    pub ulp_error: f64,
+}
+
+// num / den in decimal scientific notation, the mantissa rounded to f64
+fn scientific(num: &BigUint, den: &BigUint) -> (f64, i64) {
+    if num.bits() == 0 {
+        return (0.0, 0);
+    }
+    let f64_of = |num: &BigUint, den: &BigUint| {
+        let num = BigInt::from(num.clone());
+        round_rational::<f64>(&num, den, &FpEnv::default()).0
+    };
+    // num / den scaled by 10^-exp
+    let scaled = |exp: i64| {
+        let scale = BigUint::from(10u32).pow(exp.unsigned_abs() as u32);
+        if exp >= 0 {
+            (num.clone(), den * scale)
+        } else {
+            (num * scale, den.clone())
+        }
+    };
+    // start from the binary exponent, off by at most one decade,
+    // and find the decade with exact comparisons
+    let log2 = num.bits() as i64 - den.bits() as i64;
+    let mut exp = (log2 as f64 * std::f64::consts::LOG10_2).floor() as i64;
+    let (mut num, mut den) = scaled(exp);
+    while num >= &den * 10u32 {
+        exp += 1;
+        (num, den) = scaled(exp);
+    }
+    while num < den {
+        exp -= 1;
+        (num, den) = scaled(exp);
+    }
+    // the mantissa may still round up to 10
+    let mantissa = f64_of(&num, &den);
+    if mantissa == 10.0 {
+        (1.0, exp + 1)
+    } else {
+        (mantissa, exp)
+    }
+}
+
+// compare a finite encoding to the exact value num / den
+// the errors are rounded to f64
+pub fn conversion_error<T: FloatType>(
+    bits: &BigUint,
+    num: &BigInt,
+    den: &BigUint,
+) -> Option<ConversionError> {
+    let (mantissa, exp) = to_rational::<T>(bits)?;
+    let (_, ulp_exp, _) = decode::<T>(bits);
+
+    // error = mantissa * 2^exp - num / den = err_num / err_den
+    let den = BigInt::from(den.clone());
+    let (err_num, err_den) = if exp >= 0 {
+        (((mantissa << exp as usize) * &den) - num, den.clone())
+    } else {
+        let shift = (-exp) as usize;
+        (mantissa * &den - (num << shift), den.clone() << shift)
+    };
+    let err_num = BigInt::from(err_num.magnitude().clone());
+    let err_den = err_den.magnitude().clone();
+
+    let f64_of = |num: &BigInt, den: &BigUint| round_rational::<f64>(num, den, &FpEnv::default()).0;
+    let rel_error = if num.sign() == Sign::NoSign {
+        if err_num.sign() == Sign::NoSign {
+            0.0
+        } else {
+            f64::INFINITY
+        }
+    } else {
+        f64_of(&(&err_num * &den), &(&err_den * num.magnitude()))
+    };
+    let ulp_error = if ulp_exp >= 0 {
+        f64_of(&err_num, &(&err_den << ulp_exp as usize))
+    } else {
+        f64_of(&(&err_num << (-ulp_exp) as usize), &err_den)
+    };
+    Some(ConversionError {
+        exact: err_num.sign() == Sign::NoSign,
+        abs_error: scientific(err_num.magnitude(), &err_den),
+        rel_error,
+        ulp_error,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        conversion_error, decimal_to_rational, parse_decimal, round_rational, to_rational,
+        FloatType, FpEnv, RoundingMode,
+    };
+    use half::f16;
+    use num_bigint::{BigInt, BigUint};
+
+    #[test]
+    fn test() {
+        for (num, mantissa, exp) in [
+            (12.0f64, 3i64, 2),
+            (-0.375, -3, -3),
+            (0.0, 0, 0),
+            (-0.0, 0, 0),
+            (f64::from_bits(1), 1, -1074),
+            (0.1, 3602879701896397, -55),
+        ] {
+            assert_eq!(
+                to_rational::<f64>(&num.to_biguint()),
+                Some((BigInt::from(mantissa), exp))
+            );
+        }
+        assert_eq!(to_rational::<f64>(&f64::INFINITY.to_biguint()), None);
+        assert_eq!(to_rational::<f64>(&f64::NAN.to_biguint()), None);
+
+        // round trip
+        let mut state = 0x2545f4914f6cdd1du64;
+        for _ in 0..2000 {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let a = f64::from_bits(state);
+            if !a.is_finite() {
+                continue;
+            }
+            let (mantissa, exp) = to_rational::<f64>(&a.to_biguint()).unwrap();
+            let (num, den) = if exp >= 0 {
+                (mantissa << exp as usize, BigUint::from(1u32))
+            } else {
+                (mantissa, BigUint::from(1u32) << (-exp) as usize)
+            };
+            let env = FpEnv::default();
+            let (res, flags) = round_rational::<f64>(&num, &den, &env);
+            assert_eq!(res.to_bits(), a.to_bits());
+            assert_eq!(flags.to_bits(), 0);
+            let (res, _) = round_rational::<f32>(&num, &den, &env);
+            assert_eq!(res.to_bits(), (a as f32).to_bits());
+        }
+    }
+
+    #[test]
+    fn test_conversion_error() {
+        let env = FpEnv::default();
+        let error = |s: &str| {
+            let (num, den) = decimal_to_rational(s).unwrap();
+            let (res, _) = parse_decimal::<f32>(s, &env).unwrap();
+            conversion_error::<f32>(&res.to_biguint(), &num, &den).unwrap()
+        };
+
+        let res = error("0.5");
+        assert!(res.exact);
+        assert_eq!(
+            (res.abs_error, res.rel_error, res.ulp_error),
+            ((0.0, 0), 0.0, 0.0)
+        );
+
+        // 0.1f32 = 0.100000001490116119384765625
+        let res = error("0.1");
+        assert!(!res.exact);
+        assert_eq!(res.abs_error, (1.4901161193847656, -9));
+        assert_eq!(res.rel_error, 1.4901161193847656e-8);
+        // ulp is 2^-27
+        assert_eq!(res.ulp_error, 0.2);
+
+        // halfway is half an ulp
+        let res = error("1.000000059604644775390625");
+        assert_eq!(res.ulp_error, 0.5);
+        assert_eq!(res.abs_error, (5.9604644775390625, -8));
+
+        // subnormal ulp
+        let res = error("1e-45");
+        assert_eq!(res.ulp_error, 1.0 - 1e-45 / 2f64.powi(-149));
+
+        let (num, den) = decimal_to_rational("0.1").unwrap();
+        let res = f16::from_f32(0.1);
+        let res = conversion_error::<f16>(&res.to_biguint(), &num, &den).unwrap();
+        assert_eq!(res.abs_error, (2.44140625, -5));
+
+        // errors below the f64 range
+        let (num, den) = decimal_to_rational("1e-310").unwrap();
+        let (res, _) = parse_decimal::<f64>("1e-310", &env).unwrap();
+        let res = conversion_error::<f64>(&res.to_biguint(), &num, &den).unwrap();
+        assert_eq!(res.abs_error, (3.055067249710231, -325));
+        assert_eq!(res.rel_error, 3.0550672497102307e-15);
+        assert!(conversion_error::<f16>(&f16::INFINITY.to_biguint(), &num, &den).is_none());
+        // the mantissa rounds up to the next decade
+        let s = "0.99999999000000000000000088817841970012523233890533447265625";
+        let (num, den) = decimal_to_rational(s).unwrap();
+        let (res, _) = parse_decimal::<f16>(s, &env).unwrap();
+        let res = conversion_error::<f16>(&res.to_biguint(), &num, &den).unwrap();
+        assert_eq!(res.abs_error, (1.0, -8));
+        assert!(decimal_to_rational("inf").is_none());
+        assert!(decimal_to_rational("1e99999999").is_none());
+    }
+
+    #[test]
+    fn test_stochastic() {
+        // (3*2^23 + 1) / (3*2^23) is 1 + ulp/3, rounding up a third of the time
+        let den = BigUint::from(3u32 << 23);
+        let num = BigInt::from(den.clone()) + 1;
+        let n = 20000u64;
+        let mut ups = 0;
+        let mut state = 0x2545f4914f6cdd1du64;
+        for _ in 0..n {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let env = FpEnv {
+                rounding: RoundingMode::Stochastic(state),
+                ..FpEnv::default()
+            };
+            let (res, flags) = round_rational::<f32>(&num, &den, &env);
+            assert!(flags.inexact);
+            if res == 1.0 + f32::EPSILON {
+                ups += 1;
+            } else {
+                assert_eq!(res, 1.0);
+            }
+        }
+        // unbiased within 5 sigma
+        let p = 1.0 / 3.0;
+        let sigma = (n as f64 * p * (1.0 - p)).sqrt();
+        assert!((ups as f64 - n as f64 * p).abs() <= 5.0 * sigma, "{}", ups);
+    }
+}
@@ -0,0 +1,134 @@
+use num_bigint::{BigUint, ToBigUint};
+
+use crate::{extract, range, FloatType};
+
+// rawFloat bundle of HardFloat
+// value = (-1)^sign * sig * 2^(s_exp - 2^EXP - (SIG - 1))
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RawFloat {
+    pub is_nan: bool,
+    pub is_inf: bool,
+    pub is_zero: bool,
+    pub sign: bool,
+    // SInt(EXP + 2)
+    pub s_exp: i64,
+    // UInt(SIG + 1): 0, the implicit bit and the fraction
+    pub sig: BigUint,
+}
+
+// rawFloatFromFN
+pub fn to_raw_float<T: FloatType>(num: &BigUint) -> RawFloat {
+    let zero = 0.to_biguint().unwrap();
+    let one = 1.to_biguint().unwrap();
+    let (sign, exp_in, fract_in) = extract::<T>(num);
+
+    let is_zero_exp_in = exp_in == zero;
+    let is_zero_fract_in = fract_in == zero;
+
+    // countLeadingZeros returns SIG - 2 for a zero fraction
+    let norm_dist = if is_zero_fract_in {
+        T::SIG - 2
+    } else {
+        T::SIG - 1 - fract_in.bits() as usize
+    };
+    let subnorm_fract = range::<T>(&(&fract_in << (norm_dist + 1)), T::SIG - 2, 0);
+    let mask = (1i64 << (T::EXP + 1)) - 1;
+    let adjusted_exp = if is_zero_exp_in {
+        (norm_dist as i64 ^ mask) + ((1 << (T::EXP - 1)) | 2)
+    } else {
+        exp_in.iter_u64_digits().next().unwrap_or(0) as i64 + ((1 << (T::EXP - 1)) | 1)
+    } & mask;
+
+    let is_zero = is_zero_exp_in && is_zero_fract_in;
+    let is_special = adjusted_exp >> (T::EXP - 1) == 0b11;
+    let fract = if is_zero_exp_in {
+        subnorm_fract
+    } else {
+        fract_in
+    };
+    RawFloat {
+        is_nan: is_special && !is_zero_fract_in,
+        is_inf: is_special && is_zero_fract_in,
+        is_zero,
+        sign: sign == one,
+        s_exp: adjusted_exp,
+        sig: ((!is_zero as u32).to_biguint().unwrap() << (T::SIG - 1)) | fract,
+    }
+}
+
+// rawFloatFromRecFN
+pub fn hardfloat_to_raw_float<T: FloatType>(bits: &BigUint) -> RawFloat {
+    let exp = range::<T>(bits, T::EXP + T::SIG - 1, T::SIG - 1);
+    let exp = exp.iter_u64_digits().next().unwrap_or(0) as i64;
+    let top = exp >> (T::EXP - 2);
+    let is_zero = top == 0b000;
+    let is_special = top >> 1 == 0b11;
+    RawFloat {
+        is_nan: is_special && top & 1 == 1,
+        is_inf: is_special && top & 1 == 0,
+        is_zero,
+        sign: bits.bit((T::EXP + T::SIG) as u64),
+        s_exp: exp,
+        sig: ((!is_zero as u32).to_biguint().unwrap() << (T::SIG - 1))
+            | range::<T>(bits, T::SIG - 2, 0),
+    }
+}
+
+pub fn print_raw_float(raw: &RawFloat) -> String {
+    format!(
+        "isNaN={},isInf={},isZero={},sign={},sExp={},sig={}",
+        raw.is_nan as u8, raw.is_inf as u8, raw.is_zero as u8, raw.sign as u8, raw.s_exp, raw.sig
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{hardfloat_to_raw_float, print_raw_float, to_hardfloat, to_raw_float, FloatType};
+    use half::f16;
+    use num_bigint::ToBigUint;
+
+    #[test]
+    fn test() {
+        for (num, expected) in [
+            (1.0, "isNaN=0,isInf=0,isZero=0,sign=0,sExp=256,sig=8388608"),
+            (
+                -1.5,
+                "isNaN=0,isInf=0,isZero=0,sign=1,sExp=256,sig=12582912",
+            ),
+            (
+                f32::MIN_POSITIVE,
+                "isNaN=0,isInf=0,isZero=0,sign=0,sExp=130,sig=8388608",
+            ),
+            (
+                f32::from_bits(1),
+                "isNaN=0,isInf=0,isZero=0,sign=0,sExp=107,sig=8388608",
+            ),
+            (0.0, "isNaN=0,isInf=0,isZero=1,sign=0,sExp=107,sig=0"),
+            (
+                f32::INFINITY,
+                "isNaN=0,isInf=1,isZero=0,sign=0,sExp=384,sig=8388608",
+            ),
+            (
+                f32::NAN,
+                "isNaN=1,isInf=0,isZero=0,sign=0,sExp=384,sig=12582912",
+            ),
+        ] {
+            let raw = to_raw_float::<f32>(&num.to_biguint());
+            assert_eq!(print_raw_float(&raw), expected);
+        }
+
+        // both paths agree except for the don't-care exponent of zero,
+        // and the NaN bit which is only set in the recoded exponent
+        for num in 0..=u16::MAX {
+            let bits = num.to_biguint().unwrap();
+            let from_fn = to_raw_float::<f16>(&bits);
+            let mut from_rec_fn = hardfloat_to_raw_float::<f16>(&to_hardfloat::<f16>(&bits));
+            if from_fn.is_zero {
+                from_rec_fn.s_exp = from_fn.s_exp;
+            } else if from_fn.is_nan {
+                from_rec_fn.s_exp &= !(1 << (f16::EXP - 2));
+            }
+            assert_eq!(from_fn, from_rec_fn, "{}", f16::from_bits(num));
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use num_bigint::BigUint;
+
+use crate::{
+    decode, extract, flush_input, invalid_nan, propagate_nan, round_pack, ExceptionFlags,
+    FloatType, FpEnv,
+};
+
+// nan operands, b = 0 and a = inf, None for finite a and nonzero b
+fn special<T: FloatType>(a: T, b: T, env: &FpEnv) -> Option<(T, ExceptionFlags)> {
+    let (_, exp_a, man_a) = extract::<T>(&a.to_biguint());
+    let (_, exp_b, man_b) = extract::<T>(&b.to_biguint());
+    let mut flags = ExceptionFlags::default();
+    let quiet_bit = T::SIG as u64 - 2;
+    flags.invalid = (exp_a == T::max_exp() && man_a.bits() != 0 && !man_a.bit(quiet_bit))
+        || (exp_b == T::max_exp() && man_b.bits() != 0 && !man_b.bit(quiet_bit));
+    if let Some(res) = propagate_nan(env.nan_policy, &[a, b]) {
+        return Some((res, flags));
+    }
+    if exp_a == T::max_exp() || (exp_b.bits() == 0 && man_b.bits() == 0) {
+        flags.invalid = true;
+        return Some((invalid_nan(env.nan_policy), flags));
+    }
+    if exp_b == T::max_exp() {
+        // x rem inf = x
+        return Some((a, flags));
+    }
+    None
+}
+
+// |a| = q * |b| + r with 0 <= r < |b|, all scaled by 2^-exp
+fn divide<T: FloatType>(a: T, b: T) -> (bool, BigUint, BigUint, BigUint, i64) {
+    let (sign, exp_a, sig_a) = decode::<T>(&a.to_biguint());
+    let (_, exp_b, sig_b) = decode::<T>(&b.to_biguint());
+    let exp = exp_a.min(exp_b);
+    let sig_a = sig_a << (exp_a - exp) as usize;
+    let sig_b = sig_b << (exp_b - exp) as usize;
+    let q = &sig_a / &sig_b;
+    let r = sig_a - &q * &sig_b;
+    (sign, q, r, sig_b, exp)
+}
+
+// IEEE remainder: a - n * b with n the integer nearest to a / b, ties to even
+// the result is exact and a zero result has the sign of a
+pub fn remainder<T: FloatType>(a: T, b: T, env: &FpEnv) -> (T, ExceptionFlags) {
+    let mut input_flags = ExceptionFlags::default();
+    let a = flush_input(a, env, &mut input_flags);
+    let b = flush_input(b, env, &mut input_flags);
+    if let Some((res, flags)) = special(a, b, env) {
+        return (res, flags | input_flags);
+    }
+    let (sign, q, r, sig_b, exp) = divide(a, b);
+    let twice = &r << 1;
+    let (sign, r) = if twice > sig_b || (twice == sig_b && q.bit(0)) {
+        // round n up, which flips the sign
+        (!sign, sig_b - r)
+    } else {
+        (sign, r)
+    };
+    let (bits, _) = round_pack::<T>(sign, exp, &r, env.rounding, env.tininess);
+    (T::from_biguint(&bits), input_flags)
+}
+
+// C fmod: a - n * b with n = trunc(a / b), exact with the sign of a
+pub fn fmod<T: FloatType>(a: T, b: T, env: &FpEnv) -> (T, ExceptionFlags) {
+    let mut input_flags = ExceptionFlags::default();
+    let a = flush_input(a, env, &mut input_flags);
+    let b = flush_input(b, env, &mut input_flags);
+    if let Some((res, flags)) = special(a, b, env) {
+        return (res, flags | input_flags);
+    }
+    let (sign, _, r, _, exp) = divide(a, b);
+    let (bits, _) = round_pack::<T>(sign, exp, &r, env.rounding, env.tininess);
+    (T::from_biguint(&bits), input_flags)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{fmod, remainder, FpEnv};
+    use half::f16;
+
+    // remainder from the exact fmod, with the parity of the quotient from fmod by 2b
+    fn native_remainder(a: f64, b: f64) -> f64 {
+        let r = a % b;
+        let odd = (a.abs() % (2.0 * b.abs())) >= b.abs();
+        let rest = b.abs() - r.abs();
+        if r.abs() > rest || (r.abs() == rest && odd) {
+            // a nonzero r is replaced by r - sign(r) * |b|
+            rest.copysign(-r)
+        } else {
+            r
+        }
+    }
+
+    #[test]
+    fn test() {
+        let env = FpEnv::default();
+        let mut state = 0x2545f4914f6cdd1du64;
+        for _ in 0..5000 {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let a = f64::from_bits(state);
+            // exponents close to a, and below 2^1023 so that 2b is finite
+            let exp_b = ((state >> 52) & 0x7ff)
+                .saturating_sub(state >> 58)
+                .min(0x7fd);
+            let b = f64::from_bits((state.rotate_left(23) & 0x800fffffffffffff) | (exp_b << 52));
+            if (a % b).is_nan() {
+                assert!(fmod(a, b, &env).0.is_nan());
+                assert!(remainder(a, b, &env).0.is_nan());
+                continue;
+            }
+            let (res, flags) = fmod(a, b, &env);
+            assert_eq!(res.to_bits(), (a % b).to_bits(), "{} {}", a, b);
+            assert_eq!(flags.to_bits(), 0);
+            let (res, flags) = remainder(a, b, &env);
+            let expected = native_remainder(a, b);
+            assert_eq!(res.to_bits(), expected.to_bits(), "{} {}", a, b);
+            assert_eq!(flags.to_bits(), 0);
+
+            // f32 results are exact in f64
+            let (a, b) = (a as f32, b as f32);
+            if !(a % b).is_nan() {
+                let (res, _) = remainder(a, b, &env);
+                let expected = native_remainder(a as f64, b as f64) as f32;
+                assert_eq!(res.to_bits(), expected.to_bits(), "{} {}", a, b);
+                let (res, _) = fmod(a, b, &env);
+                assert_eq!(res.to_bits(), (a % b).to_bits(), "{} {}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_special() {
+        let env = FpEnv::default();
+        for (a, b, rem, flags) in [
+            // ties go to the even quotient
+            (5.0f32, 2.0, 1.0, 0b00000),
+            (7.0, 2.0, -1.0, 0b00000),
+            (-7.0, 2.0, 1.0, 0b00000),
+            (3.0, 2.0, -1.0, 0b00000),
+            // zero results keep the sign of a
+            (-4.0, 2.0, -0.0, 0b00000),
+            (-0.0, 1.0, -0.0, 0b00000),
+            // subnormal results are exact
+            (
+                f32::MIN_POSITIVE,
+                f32::from_bits(3),
+                -f32::from_bits(1),
+                0b00000,
+            ),
+            (1.5, f32::INFINITY, 1.5, 0b00000),
+            (f32::INFINITY, 1.0, f32::NAN, 0b10000),
+            (1.0, 0.0, f32::NAN, 0b10000),
+            (f32::from_bits(0x7f800001), 1.0, f32::NAN, 0b10000),
+            (f32::NAN, 0.0, f32::NAN, 0b00000),
+        ] {
+            let (res, res_flags) = remainder(a, b, &env);
+            if rem.is_nan() {
+                assert!(res.is_nan());
+            } else {
+                assert_eq!(res.to_bits(), rem.to_bits(), "{} {}", a, b);
+            }
+            assert_eq!(res_flags.to_bits(), flags, "{} {}", a, b);
+        }
+        let (res, flags) = fmod(f16::from_f32(-7.0), f16::from_f32(2.0), &env);
+        assert_eq!((res, flags.to_bits()), (f16::from_f32(-1.0), 0));
+    }
+}
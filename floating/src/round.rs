@@ -0,0 +1,325 @@
+use num_bigint::{BigUint, ToBigUint};
+use std::cmp::max;
+
+use crate::FloatType;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RoundingMode {
+    // roundTiesToEven
+    #[default]
+    NearestEven,
+    // roundTowardZero
+    TowardZero,
+    // roundTowardNegative
+    TowardNegative,
+    // roundTowardPositive
+    TowardPositive,
+    // roundTiesToAway
+    NearestMaxMag,
+    // round away from zero with probability equal to the discarded fraction,
+    // drawn from these caller-supplied random bits so results reproduce
+    Stochastic(u64),
+}
+
+// when to detect tininess for the underflow flag
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Tininess {
+    BeforeRounding,
+    #[default]
+    AfterRounding,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ExceptionFlags {
+    pub invalid: bool,
+    pub div_by_zero: bool,
+    pub overflow: bool,
+    pub underflow: bool,
+    pub inexact: bool,
+    // ARM IDC: a subnormal input was flushed to zero, not part of the IEEE flags
+    pub input_denormal: bool,
+}
+
+impl ExceptionFlags {
+    // same bit order as HardFloat and RISC-V fflags: NV DZ OF UF NX
+    pub fn to_bits(self) -> u8 {
+        ((self.invalid as u8) << 4)
+            | ((self.div_by_zero as u8) << 3)
+            | ((self.overflow as u8) << 2)
+            | ((self.underflow as u8) << 1)
+            | (self.inexact as u8)
+    }
+}
+
+impl std::ops::BitOr for ExceptionFlags {
+    type Output = ExceptionFlags;
+
+    fn bitor(self, rhs: ExceptionFlags) -> ExceptionFlags {
+        ExceptionFlags {
+            invalid: self.invalid || rhs.invalid,
+            div_by_zero: self.div_by_zero || rhs.div_by_zero,
+            overflow: self.overflow || rhs.overflow,
+            underflow: self.underflow || rhs.underflow,
+            inexact: self.inexact || rhs.inexact,
+            input_denormal: self.input_denormal || rhs.input_denormal,
+        }
+    }
+}
+
+impl std::ops::BitOrAssign for ExceptionFlags {
+    fn bitor_assign(&mut self, rhs: ExceptionFlags) {
+        *self = *self | rhs;
+    }
+}
+
+// stochastic rounding decision for the discarded fraction rem / 2^shift:
+// its top 64 bits plus the random bits carry out, which happens with
+// probability rem / 2^shift for uniform random bits
+pub(crate) fn stochastic_up(rem: &BigUint, shift: u64, random: u64) -> bool {
+    let fraction = if shift <= 64 {
+        rem << (64 - shift)
+    } else {
+        rem >> (shift - 64)
+    };
+    let fraction = fraction.to_u64_digits().first().copied().unwrap_or(0);
+    fraction.checked_add(random).is_none()
+}
+
+// round sig * 2^exp so that the lsb has weight 2^lsb
+// returns (rounded sig, inexact)
+pub(crate) fn round_at(
+    sign: bool,
+    sig: &BigUint,
+    exp: i64,
+    lsb: i64,
+    rm: RoundingMode,
+) -> (BigUint, bool) {
+    let shift = lsb - exp;
+    if shift <= 0 {
+        return (sig << (-shift) as usize, false);
+    }
+    let shift = shift as u64;
+    let one = 1.to_biguint().unwrap();
+    let mut res: BigUint = sig >> shift;
+    let rem = sig - (&res << shift);
+    let half = &one << (shift - 1);
+    let inexact = rem.bits() != 0;
+    let increment = match rm {
+        RoundingMode::NearestEven => rem > half || (rem == half && res.bit(0)),
+        RoundingMode::NearestMaxMag => rem >= half,
+        RoundingMode::TowardZero => false,
+        RoundingMode::TowardNegative => inexact && sign,
+        RoundingMode::TowardPositive => inexact && !sign,
+        RoundingMode::Stochastic(random) => stochastic_up(&rem, shift, random),
+    };
+    if increment {
+        res += one;
+    }
+    (res, inexact)
+}
+
+// round (-1)^sign * sig * 2^exp to the format T and return its bits
+// a sticky bit can be kept in the lsb of sig,
+// as long as sig has at least two more bits than T::SIG
+pub fn round_pack<T: FloatType>(
+    sign: bool,
+    exp: i64,
+    sig: &BigUint,
+    rm: RoundingMode,
+    tininess: Tininess,
+) -> (BigUint, ExceptionFlags) {
+    let one = 1.to_biguint().unwrap();
+    let mut flags = ExceptionFlags::default();
+    let sign_bit = (sign as u32).to_biguint().unwrap() << (T::WIDTH - 1);
+    if sig.bits() == 0 {
+        return (sign_bit, flags);
+    }
+
+    let bias = (1i64 << (T::EXP - 1)) - 1;
+    let emin = 1 - bias;
+    let prec = T::SIG as i64;
+    // exponent of the leading one
+    let e = exp + sig.bits() as i64 - 1;
+
+    // subnormal numbers have their lsb fixed at 2^(emin - prec + 1)
+    let mut lsb = max(e, emin) - (prec - 1);
+    let (mut man, inexact) = round_at(sign, sig, exp, lsb, rm);
+    if man.bits() as i64 > prec {
+        // carry out of rounding: 10.000 -> 1.000
+        man >>= 1;
+        lsb += 1;
+    }
+
+    if inexact {
+        flags.inexact = true;
+        let tiny = match tininess {
+            Tininess::BeforeRounding => e < emin,
+            Tininess::AfterRounding => {
+                // round as if the exponent range were unbounded
+                let (man, _) = round_at(sign, sig, exp, e - (prec - 1), rm);
+                e + (man.bits() as i64 - prec) < emin
+            }
+        };
+        flags.underflow = tiny;
+    }
+
+    let biased = if man.bits() as i64 == prec {
+        lsb + prec - 1 + bias
+    } else {
+        // subnormal
+        0
+    };
+    if biased >= (1i64 << T::EXP) - 1 {
+        flags.overflow = true;
+        flags.inexact = true;
+        let to_inf = match rm {
+            // stochastic rounding only overflows when rounding away from zero
+            RoundingMode::NearestEven
+            | RoundingMode::NearestMaxMag
+            | RoundingMode::Stochastic(_) => true,
+            RoundingMode::TowardZero => false,
+            RoundingMode::TowardNegative => sign,
+            RoundingMode::TowardPositive => !sign,
+        };
+        let res = if to_inf {
+            T::max_exp() << (T::SIG - 1)
+        } else {
+            // largest finite number
+            (T::max_exp() << (T::SIG - 1)) - one
+        };
+        return (sign_bit | res, flags);
+    }
+
+    let man = man & ((&one << (T::SIG - 1)) - &one);
+    (
+        sign_bit | (biased.to_biguint().unwrap() << (T::SIG - 1)) | man,
+        flags,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{round_pack, ExceptionFlags, FloatType, RoundingMode, Tininess};
+    use num_bigint::ToBigUint;
+
+    #[test]
+    fn test() {
+        let rne = RoundingMode::NearestEven;
+        let rtz = RoundingMode::TowardZero;
+        let rdn = RoundingMode::TowardNegative;
+        let rup = RoundingMode::TowardPositive;
+        let rmm = RoundingMode::NearestMaxMag;
+        let after = Tininess::AfterRounding;
+        for (sign, exp, sig, rm, expected, flags) in [
+            // exact
+            (false, 0, 1u64, rne, 1.0f32, 0b00000),
+            (true, -1, 3, rne, -1.5, 0b00000),
+            // ties
+            (false, -24, (1 << 24) + 1, rne, 1.0, 0b00001),
+            (
+                false,
+                -24,
+                (1 << 24) + 3,
+                rne,
+                1.0 + f32::EPSILON * 2.0,
+                0b00001,
+            ),
+            (false, -24, (1 << 24) + 1, rmm, 1.0 + f32::EPSILON, 0b00001),
+            // directed
+            (false, -25, (1 << 25) + 1, rtz, 1.0, 0b00001),
+            (false, -25, (1 << 25) + 1, rup, 1.0 + f32::EPSILON, 0b00001),
+            (true, -25, (1 << 25) + 1, rup, -1.0, 0b00001),
+            (true, -25, (1 << 25) + 1, rdn, -1.0 - f32::EPSILON, 0b00001),
+            // overflow
+            (false, 128, 1, rne, f32::INFINITY, 0b00101),
+            (false, 128, 1, rtz, f32::MAX, 0b00101),
+            (true, 128, 1, rup, -f32::MAX, 0b00101),
+            // round up to the largest exponent overflows
+            (false, 104, (1 << 24) - 1, rne, f32::MAX, 0b00000),
+            (false, 103, (1 << 25) - 1, rne, f32::INFINITY, 0b00101),
+            // subnormal
+            (false, -149, 1, rne, f32::from_bits(1), 0b00000),
+            (false, -151, 3, rne, f32::from_bits(1), 0b00011),
+            (false, -151, 1, rne, 0.0, 0b00011),
+            (true, -151, 1, rdn, -f32::from_bits(1), 0b00011),
+            // round up to the smallest normal number is not tiny after rounding
+            (false, -151, (1 << 25) - 1, rne, f32::MIN_POSITIVE, 0b00001),
+        ] {
+            let (bits, res_flags) =
+                round_pack::<f32>(sign, exp, &sig.to_biguint().unwrap(), rm, after);
+            assert_eq!(
+                f32::from_biguint(&bits).to_bits(),
+                expected.to_bits(),
+                "{} {} {}",
+                sign,
+                exp,
+                sig
+            );
+            assert_eq!(res_flags.to_bits(), flags, "{} {} {}", sign, exp, sig);
+        }
+
+        // tininess before rounding
+        let (_, flags) = round_pack::<f32>(
+            false,
+            -151,
+            &((1u32 << 25) - 1).to_biguint().unwrap(),
+            rne,
+            Tininess::BeforeRounding,
+        );
+        assert_eq!(
+            flags,
+            ExceptionFlags {
+                underflow: true,
+                inexact: true,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_stochastic() {
+        let after = Tininess::AfterRounding;
+        // the discarded fraction, in 1/1024 ulp, decides how often rounding goes up
+        let n = 20000u64;
+        let mut state = 0x2545f4914f6cdd1du64;
+        for fraction in [0u64, 1, 100, 512, 700, 1023] {
+            let sig = ((1u64 << 23) << 10) + fraction;
+            let mut ups = 0;
+            for _ in 0..n {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                let rm = RoundingMode::Stochastic(state);
+                let (bits, flags) =
+                    round_pack::<f32>(true, -33, &sig.to_biguint().unwrap(), rm, after);
+                let res = f32::from_biguint(&bits);
+                assert_eq!(flags.inexact, fraction != 0);
+                if res == -1.0 - f32::EPSILON {
+                    ups += 1;
+                } else {
+                    assert_eq!(res, -1.0);
+                }
+            }
+            // unbiased: the mean of the results is the exact value, within 5 sigma
+            let p = fraction as f64 / 1024.0;
+            let sigma = (n as f64 * p * (1.0 - p)).sqrt();
+            assert!(
+                (ups as f64 - n as f64 * p).abs() <= 5.0 * sigma,
+                "{} {}",
+                fraction,
+                ups
+            );
+        }
+
+        // random bits select the result: rem + random carries out
+        let sig = ((1u32 << 23) << 2) + 1;
+        for (random, expected) in [(0, 1.0f32), (3 << 62, 1.0 + f32::EPSILON)] {
+            let rm = RoundingMode::Stochastic(random);
+            let (bits, _) = round_pack::<f32>(false, -25, &sig.to_biguint().unwrap(), rm, after);
+            assert_eq!(f32::from_biguint(&bits), expected);
+        }
+        let rm = RoundingMode::Stochastic((3 << 62) - 1);
+        let (bits, _) = round_pack::<f32>(false, -25, &sig.to_biguint().unwrap(), rm, after);
+        assert_eq!(f32::from_biguint(&bits), 1.0);
+    }
+}
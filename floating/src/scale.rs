@@ -0,0 +1,168 @@
+use num_bigint::ToBigUint;
+
+use crate::{
+    decode, extract, flush_input, propagate_nan, round_env, round_pack, ExceptionFlags, FloatType,
+    FpEnv,
+};
+
+// IEEE scaleB: a * 2^n, rounded like any other operation
+pub fn scale_b<T: FloatType>(a: T, n: i64, env: &FpEnv) -> (T, ExceptionFlags) {
+    let mut flags = ExceptionFlags::default();
+    let a = flush_input(a, env, &mut flags);
+    let num_a = a.to_biguint();
+    let (_, exp_a, man_a) = extract::<T>(&num_a);
+    if exp_a == T::max_exp() {
+        if man_a.bits() != 0 {
+            flags.invalid = !man_a.bit(T::SIG as u64 - 2);
+            return (propagate_nan(env.nan_policy, &[a]).unwrap(), flags);
+        }
+        // inf
+        return (a, flags);
+    }
+
+    let (sign, exp, sig) = decode::<T>(&num_a);
+    // keep the exponent small enough for i64, the result saturates long before
+    let bound = (1i64 << (T::EXP + 1)) + T::SIG as i64;
+    let exp = exp + n.clamp(-bound, bound);
+    let (res, round_flags) = round_env::<T>(sign, exp, &sig, env);
+    (res, flags | round_flags)
+}
+
+// IEEE logB as C logb: the exponent of a as if it were normalized,
+// logB(0) is -inf and raises divide by zero
+pub fn log_b<T: FloatType>(a: T, env: &FpEnv) -> (T, ExceptionFlags) {
+    let mut flags = ExceptionFlags::default();
+    let a = flush_input(a, env, &mut flags);
+    let num_a = a.to_biguint();
+    let (_, exp_a, man_a) = extract::<T>(&num_a);
+    let (sign, exp) = if exp_a == T::max_exp() {
+        if man_a.bits() != 0 {
+            flags.invalid = !man_a.bit(T::SIG as u64 - 2);
+            return (propagate_nan(env.nan_policy, &[a]).unwrap(), flags);
+        }
+        // logB(inf) = +inf
+        return (T::from_biguint(&(T::max_exp() << (T::SIG - 1))), flags);
+    } else if exp_a.bits() == 0 && man_a.bits() == 0 {
+        flags.div_by_zero = true;
+        // -inf
+        let sign_bit = 1.to_biguint().unwrap() << (T::WIDTH - 1);
+        return (
+            T::from_biguint(&(sign_bit | (T::max_exp() << (T::SIG - 1)))),
+            flags,
+        );
+    } else {
+        let (_, exp, sig) = decode::<T>(&num_a);
+        let exp = exp + sig.bits() as i64 - 1;
+        (exp < 0, exp.unsigned_abs())
+    };
+    // exact, the exponent range of T is far below 2^SIG
+    let (bits, round_flags) = round_pack::<T>(sign, 0, &exp.into(), env.rounding, env.tininess);
+    (T::from_biguint(&bits), flags | round_flags)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{log_b, scale_b, softfloat_convert, CustomFloat, FloatType, FpEnv, CUSTOM_MAX_EXP};
+
+    // exponent of the leading one
+    fn native_log_b(a: f64) -> f64 {
+        let bits = a.to_bits() & 0x7fffffffffffffff;
+        let field = (bits >> 52) as i64;
+        if field == 0 {
+            (-1074 + 63 - bits.leading_zeros() as i64) as f64
+        } else {
+            (field - 1023) as f64
+        }
+    }
+
+    #[test]
+    fn test() {
+        let env = FpEnv::default();
+        let mut state = 0x2545f4914f6cdd1du64;
+        for _ in 0..5000 {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let a = f64::from_bits(state);
+            if !a.is_finite() {
+                continue;
+            }
+            // 2^n is a normal f64, so a * 2^n is rounded once
+            let n = (state >> 40) as i64 % 1000;
+            let (res, _) = scale_b(a, n, &env);
+            assert_eq!(
+                res.to_bits(),
+                (a * 2f64.powi(n as i32)).to_bits(),
+                "{} {}",
+                a,
+                n
+            );
+            let (res, _) = scale_b(a as f32, n / 8, &env);
+            let expected = a as f32 * 2f32.powi(n as i32 / 8);
+            assert_eq!(res.to_bits(), expected.to_bits(), "{} {}", a, n);
+
+            if a != 0.0 {
+                let (res, flags) = log_b(a, &env);
+                assert_eq!(res, native_log_b(a), "{}", a);
+                assert_eq!(flags.to_bits(), 0);
+            }
+            let c = a as f32;
+            if c != 0.0 && c.is_finite() {
+                let (res, _) = log_b(c, &env);
+                assert_eq!(res as f64, native_log_b(c as f64), "{}", c);
+            }
+        }
+    }
+
+    #[test]
+    fn test_special() {
+        let env = FpEnv::default();
+        let tiny = f64::from_bits(1);
+        for (a, n, expected, flags) in [
+            (1.0f64, 1024, f64::INFINITY, 0b00101),
+            (-1.0, i64::MAX, f64::NEG_INFINITY, 0b00101),
+            (1.0, -1074, tiny, 0b00000),
+            (1.0, -1075, 0.0, 0b00011),
+            (1.5, -1074, 2.0 * tiny, 0b00011),
+            (f64::MAX, i64::MIN, 0.0, 0b00011),
+            (tiny, 1074, 1.0, 0b00000),
+            (-0.0, 5, -0.0, 0b00000),
+            (f64::NEG_INFINITY, -5, f64::NEG_INFINITY, 0b00000),
+        ] {
+            let (res, res_flags) = scale_b(a, n, &env);
+            assert_eq!(res.to_bits(), expected.to_bits(), "{} {}", a, n);
+            assert_eq!(res_flags.to_bits(), flags, "{} {}", a, n);
+        }
+        let (res, flags) = scale_b(f64::from_bits(0x7ff0000000000001), 1, &env);
+        assert!(res.is_nan());
+        assert_eq!(flags.to_bits(), 0b10000);
+
+        for (a, expected, flags) in [
+            (0.0f64, f64::NEG_INFINITY, 0b01000),
+            (-0.0, f64::NEG_INFINITY, 0b01000),
+            (f64::NEG_INFINITY, f64::INFINITY, 0b00000),
+            (tiny, -1074.0, 0b00000),
+            (-0.75, -1.0, 0b00000),
+        ] {
+            let (res, res_flags) = log_b(a, &env);
+            assert_eq!(res, expected, "{}", a);
+            assert_eq!(res_flags.to_bits(), flags, "{}", a);
+        }
+        // the exponent range of f16 and a wide custom format
+        let (res, _) = log_b(half::f16::from_bits(1), &env);
+        assert_eq!(res.to_f32(), -24.0);
+        let (res, _) = log_b(CustomFloat::<15, 113>(1), &env);
+        let (expected, _) = softfloat_convert::<f64, CustomFloat<15, 113>>(-16494.0, &env);
+        assert_eq!(res.0, expected.0);
+
+        // the widest exponent of CustomFloat
+        type Wide = CustomFloat<CUSTOM_MAX_EXP, 108>;
+        let tiny: Wide = CustomFloat(1);
+        let (huge, _) = scale_b(tiny, 1 << CUSTOM_MAX_EXP, &env);
+        assert_eq!(scale_b(huge, -(1 << CUSTOM_MAX_EXP), &env).0, tiny);
+        let (inf, flags) = scale_b(tiny, i64::MAX, &env);
+        assert_eq!(inf.to_biguint(), Wide::max_exp() << 107);
+        assert_eq!(flags.to_bits(), 0b00101);
+        assert_eq!(scale_b(huge, i64::MIN, &env).0, CustomFloat(0));
+    }
+}
@@ -0,0 +1,57 @@
+use num_bigint::ToBigUint;
+
+use crate::{extract, pack, FloatType};
+
+// IEEE negate, abs and copySign only touch the sign bit,
+// so they are exact, raise no flags and keep nan payloads
+
+pub fn negate<T: FloatType>(a: T) -> T {
+    let one = 1.to_biguint().unwrap();
+    let (sign, exp, man) = extract::<T>(&a.to_biguint());
+    T::from_biguint(&pack::<T>(&(one - sign), &exp, &man))
+}
+
+pub fn abs<T: FloatType>(a: T) -> T {
+    let (_, exp, man) = extract::<T>(&a.to_biguint());
+    T::from_biguint(&pack::<T>(&0.to_biguint().unwrap(), &exp, &man))
+}
+
+// magnitude of a with the sign of b
+pub fn copy_sign<T: FloatType>(a: T, b: T) -> T {
+    let (_, exp, man) = extract::<T>(&a.to_biguint());
+    let (sign, _, _) = extract::<T>(&b.to_biguint());
+    T::from_biguint(&pack::<T>(&sign, &exp, &man))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{abs, copy_sign, negate, CustomFloat};
+    use half::f16;
+
+    #[test]
+    fn test() {
+        let mut state = 0x2545f4914f6cdd1du64;
+        for _ in 0..2000 {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let a = f64::from_bits(state);
+            let b = f64::from_bits(state.rotate_left(1));
+            assert_eq!(negate(a).to_bits(), (-a).to_bits());
+            assert_eq!(abs(a).to_bits(), a.abs().to_bits());
+            assert_eq!(copy_sign(a, b).to_bits(), a.copysign(b).to_bits());
+            let (a, b) = (a as f32, b as f32);
+            assert_eq!(negate(a).to_bits(), (-a).to_bits());
+            assert_eq!(abs(a).to_bits(), a.abs().to_bits());
+            assert_eq!(copy_sign(a, b).to_bits(), a.copysign(b).to_bits());
+        }
+
+        // signaling nan payloads are kept
+        let snan = f32::from_bits(0x7f800001);
+        assert_eq!(negate(snan).to_bits(), 0xff800001);
+        assert_eq!(abs(negate(snan)).to_bits(), 0x7f800001);
+        assert_eq!(copy_sign(-0.0f32, snan).to_bits(), 0);
+        assert_eq!(negate(f16::NEG_INFINITY), f16::INFINITY);
+        assert_eq!(negate(CustomFloat::<15, 113>(1)).0, (1 << 127) | 1);
+    }
+}
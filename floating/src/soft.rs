@@ -0,0 +1,719 @@
+use num_bigint::{BigInt, BigUint};
+use num_traits::{Float, Num, NumCast, One, ToPrimitive, Zero};
+use std::cmp::Ordering;
+use std::fmt::{Binary, Display, LowerHex};
+use std::hash::{Hash, Hasher};
+use std::num::FpCategory;
+use std::ops::{
+    Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Rem, RemAssign, Sub, SubAssign,
+};
+use std::str::FromStr;
+
+use crate::{
+    abs, copy_sign, cr_atan, cr_cos, cr_exp, cr_log, cr_log2, cr_pow, cr_pown, cr_sin, cr_tan,
+    decode, default_nan, float_to_int, fmod, format_info, int_to_float, maximum_number,
+    minimum_number, negate, parse_decimal, parse_hex_float, propagate_nan, round_to_integral,
+    softfloat_add, softfloat_classify, softfloat_compare, softfloat_convert, softfloat_div,
+    softfloat_mul, softfloat_sqrt, softfloat_sub, total_cmp, BigFloat, FloatType, FpEnv,
+    RoundingMode,
+};
+
+// a float of format T whose operators run through the soft-float model
+// in the default environment: round to nearest even, no flushing, x86 nans
+// flags are dropped, call the softfloat_* functions to see them
+// == and < follow IEEE, so nan != nan and -0 == +0; Total orders and hashes
+#[derive(Clone, Copy, Debug)]
+pub struct Soft<T: FloatType>(pub T);
+
+// totalOrder of IEEE 754 as Ord, equal exactly when the bits are
+#[derive(Clone, Copy, Debug)]
+pub struct Total<T: FloatType>(pub T);
+
+// error of Soft::from_str
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseSoftError;
+
+impl Display for ParseSoftError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid float literal")
+    }
+}
+
+impl std::error::Error for ParseSoftError {}
+
+fn env() -> FpEnv {
+    FpEnv::default()
+}
+
+impl<T: FloatType> Soft<T> {
+    fn bits(self) -> BigUint {
+        self.0.to_biguint()
+    }
+
+    fn from_bits(bits: &BigUint) -> Soft<T> {
+        Soft(T::from_biguint(bits))
+    }
+
+    fn sign_bit(self) -> bool {
+        self.bits().bit(T::WIDTH as u64 - 1)
+    }
+
+    fn integral(self, rm: RoundingMode) -> Soft<T> {
+        Soft(round_to_integral(self.0, rm, &env()).0)
+    }
+
+    fn to_int(self, signed: bool, width: usize) -> Option<BigInt> {
+        let (res, flags) = float_to_int(self.0, signed, width, RoundingMode::TowardZero, &env());
+        (!flags.invalid).then_some(res)
+    }
+
+    fn from_int(int: &BigInt) -> Soft<T> {
+        Soft(int_to_float(int, &env()).0)
+    }
+
+    // functions without a soft-float model go through the f64 libm,
+    // so they are not correctly rounded and lose precision for formats wider than f64
+    fn via_f64(self, f: impl Fn(f64) -> f64) -> Soft<T> {
+        let (a, _) = softfloat_convert::<T, f64>(self.0, &env());
+        Soft(softfloat_convert::<f64, T>(f(a), &env()).0)
+    }
+
+    fn via_f64_2(self, other: Soft<T>, f: impl Fn(f64, f64) -> f64) -> Soft<T> {
+        let (b, _) = softfloat_convert::<T, f64>(other.0, &env());
+        self.via_f64(|a| f(a, b))
+    }
+
+    // a * b + c with a single rounding
+    fn fused_mul_add(self, b: Soft<T>, c: Soft<T>) -> Soft<T> {
+        if let Some(res) = propagate_nan(env().nan_policy, &[self.0, b.0, c.0]) {
+            return Soft(res);
+        }
+        let exact = |a: Soft<T>| BigFloat::from_float(a.0);
+        match (exact(self), exact(b), exact(c)) {
+            (Some(a), Some(b), Some(c)) => {
+                // products and sums of finite numbers are exact at these precisions
+                let (product, _) = a.mul(&b, 2 * T::SIG, RoundingMode::TowardZero);
+                let (sum, _) = product.add(&c, usize::MAX >> 1, env().rounding);
+                Soft(sum.to_float::<T>(&env()).0)
+            }
+            // a finite product cannot overflow before it is added to inf,
+            // c is not a nan here
+            (Some(_), Some(_), None) => c,
+            _ => self * b + c,
+        }
+    }
+}
+
+impl<T: FloatType> From<T> for Soft<T> {
+    fn from(a: T) -> Soft<T> {
+        Soft(a)
+    }
+}
+
+impl<T: FloatType> Add for Soft<T> {
+    type Output = Soft<T>;
+
+    fn add(self, rhs: Soft<T>) -> Soft<T> {
+        Soft(softfloat_add(self.0, rhs.0, &env()).0)
+    }
+}
+
+impl<T: FloatType> Sub for Soft<T> {
+    type Output = Soft<T>;
+
+    fn sub(self, rhs: Soft<T>) -> Soft<T> {
+        Soft(softfloat_sub(self.0, rhs.0, &env()).0)
+    }
+}
+
+impl<T: FloatType> Mul for Soft<T> {
+    type Output = Soft<T>;
+
+    fn mul(self, rhs: Soft<T>) -> Soft<T> {
+        Soft(softfloat_mul(self.0, rhs.0, &env()).0)
+    }
+}
+
+impl<T: FloatType> Div for Soft<T> {
+    type Output = Soft<T>;
+
+    fn div(self, rhs: Soft<T>) -> Soft<T> {
+        Soft(softfloat_div(self.0, rhs.0, &env()).0)
+    }
+}
+
+// truncated remainder like % of f32 and f64
+impl<T: FloatType> Rem for Soft<T> {
+    type Output = Soft<T>;
+
+    fn rem(self, rhs: Soft<T>) -> Soft<T> {
+        Soft(fmod(self.0, rhs.0, &env()).0)
+    }
+}
+
+impl<T: FloatType> Neg for Soft<T> {
+    type Output = Soft<T>;
+
+    fn neg(self) -> Soft<T> {
+        Soft(negate(self.0))
+    }
+}
+
+impl<T: FloatType> AddAssign for Soft<T> {
+    fn add_assign(&mut self, rhs: Soft<T>) {
+        *self = *self + rhs;
+    }
+}
+
+impl<T: FloatType> SubAssign for Soft<T> {
+    fn sub_assign(&mut self, rhs: Soft<T>) {
+        *self = *self - rhs;
+    }
+}
+
+impl<T: FloatType> MulAssign for Soft<T> {
+    fn mul_assign(&mut self, rhs: Soft<T>) {
+        *self = *self * rhs;
+    }
+}
+
+impl<T: FloatType> DivAssign for Soft<T> {
+    fn div_assign(&mut self, rhs: Soft<T>) {
+        *self = *self / rhs;
+    }
+}
+
+impl<T: FloatType> RemAssign for Soft<T> {
+    fn rem_assign(&mut self, rhs: Soft<T>) {
+        *self = *self % rhs;
+    }
+}
+
+impl<T: FloatType> PartialEq for Soft<T> {
+    fn eq(&self, other: &Soft<T>) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+impl<T: FloatType> PartialOrd for Soft<T> {
+    fn partial_cmp(&self, other: &Soft<T>) -> Option<Ordering> {
+        softfloat_compare(self.0, other.0, false, &env()).0
+    }
+}
+
+impl<T: FloatType> PartialEq for Total<T> {
+    fn eq(&self, other: &Total<T>) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T: FloatType> Eq for Total<T> {}
+
+impl<T: FloatType> PartialOrd for Total<T> {
+    fn partial_cmp(&self, other: &Total<T>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: FloatType> Ord for Total<T> {
+    fn cmp(&self, other: &Total<T>) -> Ordering {
+        total_cmp(self.0, other.0)
+    }
+}
+
+impl<T: FloatType> Hash for Total<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.to_biguint().hash(state);
+    }
+}
+
+// decimal like "-1.5e3", hex float like "0x1.8p1", "inf" or "nan",
+// rounded to nearest even
+impl<T: FloatType> FromStr for Soft<T> {
+    type Err = ParseSoftError;
+
+    fn from_str(s: &str) -> Result<Soft<T>, ParseSoftError> {
+        let unsigned = s.trim_start_matches(['+', '-']);
+        let res = if unsigned.starts_with("0x") || unsigned.starts_with("0X") {
+            parse_hex_float::<T>(s, &env())
+        } else {
+            parse_decimal::<T>(s, &env())
+        };
+        res.map(|(res, _)| Soft(res)).ok_or(ParseSoftError)
+    }
+}
+
+impl<T: FloatType> Display for Soft<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+// the encoding, like {:x} of to_bits()
+impl<T: FloatType> LowerHex for Soft<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        LowerHex::fmt(&self.bits(), f)
+    }
+}
+
+impl<T: FloatType> Binary for Soft<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Binary::fmt(&self.bits(), f)
+    }
+}
+
+impl<T: FloatType> Zero for Soft<T> {
+    fn zero() -> Soft<T> {
+        Soft::from_bits(&BigUint::default())
+    }
+
+    fn is_zero(&self) -> bool {
+        softfloat_classify(self.0) == FpCategory::Zero
+    }
+}
+
+impl<T: FloatType> One for Soft<T> {
+    fn one() -> Soft<T> {
+        Soft::from_int(&BigInt::from(1))
+    }
+}
+
+impl<T: FloatType> Num for Soft<T> {
+    type FromStrRadixErr = ParseSoftError;
+
+    // only radix 10, and 16 for hex floats with their 0x prefix
+    fn from_str_radix(s: &str, radix: u32) -> Result<Soft<T>, ParseSoftError> {
+        match radix {
+            10 | 16 => s.parse(),
+            _ => Err(ParseSoftError),
+        }
+    }
+}
+
+// truncating conversions, None for nan and out of range values
+impl<T: FloatType> ToPrimitive for Soft<T> {
+    fn to_i64(&self) -> Option<i64> {
+        self.to_int(true, 64)?.try_into().ok()
+    }
+
+    fn to_u64(&self) -> Option<u64> {
+        self.to_int(false, 64)?.try_into().ok()
+    }
+
+    fn to_i128(&self) -> Option<i128> {
+        self.to_int(true, 128)?.try_into().ok()
+    }
+
+    fn to_u128(&self) -> Option<u128> {
+        self.to_int(false, 128)?.try_into().ok()
+    }
+
+    fn to_f32(&self) -> Option<f32> {
+        Some(softfloat_convert::<T, f32>(self.0, &env()).0)
+    }
+
+    fn to_f64(&self) -> Option<f64> {
+        Some(softfloat_convert::<T, f64>(self.0, &env()).0)
+    }
+}
+
+impl<T: FloatType> NumCast for Soft<T> {
+    fn from<N: ToPrimitive>(n: N) -> Option<Soft<T>> {
+        let float = n.to_f64()?;
+        // integers round once from their exact value,
+        // they are the only sources whose truncation converts back to the same f64
+        if let Some(int) = n.to_i128().filter(|&int| int as f64 == float) {
+            return Some(Soft::from_int(&BigInt::from(int)));
+        }
+        if let Some(int) = n.to_u128().filter(|&int| int as f64 == float) {
+            return Some(Soft::from_int(&BigInt::from(int)));
+        }
+        Some(Soft(softfloat_convert::<f64, T>(float, &env()).0))
+    }
+}
+
+impl<T: FloatType> Float for Soft<T> {
+    fn nan() -> Soft<T> {
+        Soft(default_nan(false))
+    }
+
+    fn infinity() -> Soft<T> {
+        Soft::from_bits(&(T::max_exp() << (T::SIG - 1)))
+    }
+
+    fn neg_infinity() -> Soft<T> {
+        -Soft::infinity()
+    }
+
+    fn neg_zero() -> Soft<T> {
+        -Soft::zero()
+    }
+
+    fn min_value() -> Soft<T> {
+        -Soft::max_value()
+    }
+
+    fn min_positive_value() -> Soft<T> {
+        Soft::from_bits(&format_info::<T>().min_normal.bits)
+    }
+
+    fn epsilon() -> Soft<T> {
+        Soft::from_bits(&format_info::<T>().epsilon.bits)
+    }
+
+    fn max_value() -> Soft<T> {
+        Soft::from_bits(&format_info::<T>().max_finite.bits)
+    }
+
+    fn is_nan(self) -> bool {
+        self.classify() == FpCategory::Nan
+    }
+
+    fn is_infinite(self) -> bool {
+        self.classify() == FpCategory::Infinite
+    }
+
+    fn is_finite(self) -> bool {
+        !matches!(self.classify(), FpCategory::Nan | FpCategory::Infinite)
+    }
+
+    fn is_normal(self) -> bool {
+        self.classify() == FpCategory::Normal
+    }
+
+    fn classify(self) -> FpCategory {
+        softfloat_classify(self.0)
+    }
+
+    fn floor(self) -> Soft<T> {
+        self.integral(RoundingMode::TowardNegative)
+    }
+
+    fn ceil(self) -> Soft<T> {
+        self.integral(RoundingMode::TowardPositive)
+    }
+
+    fn round(self) -> Soft<T> {
+        self.integral(RoundingMode::NearestMaxMag)
+    }
+
+    fn trunc(self) -> Soft<T> {
+        self.integral(RoundingMode::TowardZero)
+    }
+
+    fn fract(self) -> Soft<T> {
+        self - self.trunc()
+    }
+
+    fn abs(self) -> Soft<T> {
+        Soft(abs(self.0))
+    }
+
+    fn signum(self) -> Soft<T> {
+        if self.is_nan() {
+            self
+        } else {
+            Soft(copy_sign(Soft::<T>::one().0, self.0))
+        }
+    }
+
+    fn is_sign_positive(self) -> bool {
+        !self.sign_bit()
+    }
+
+    fn is_sign_negative(self) -> bool {
+        self.sign_bit()
+    }
+
+    fn mul_add(self, a: Soft<T>, b: Soft<T>) -> Soft<T> {
+        self.fused_mul_add(a, b)
+    }
+
+    fn recip(self) -> Soft<T> {
+        Soft::one() / self
+    }
+
+    fn powi(self, n: i32) -> Soft<T> {
+        Soft(cr_pown(self.0, n as i64, &env()).0)
+    }
+
+    fn powf(self, n: Soft<T>) -> Soft<T> {
+        Soft(cr_pow(self.0, n.0, &env()).0)
+    }
+
+    fn sqrt(self) -> Soft<T> {
+        Soft(softfloat_sqrt(self.0, &env()).0)
+    }
+
+    fn exp(self) -> Soft<T> {
+        Soft(cr_exp(self.0, &env()).0)
+    }
+
+    fn exp2(self) -> Soft<T> {
+        Soft::from_int(&BigInt::from(2)).powf(self)
+    }
+
+    fn ln(self) -> Soft<T> {
+        Soft(cr_log(self.0, &env()).0)
+    }
+
+    fn log(self, base: Soft<T>) -> Soft<T> {
+        self.ln() / base.ln()
+    }
+
+    fn log2(self) -> Soft<T> {
+        Soft(cr_log2(self.0, &env()).0)
+    }
+
+    fn log10(self) -> Soft<T> {
+        self.via_f64(f64::log10)
+    }
+
+    fn max(self, other: Soft<T>) -> Soft<T> {
+        Soft(maximum_number(self.0, other.0, &env()).0)
+    }
+
+    fn min(self, other: Soft<T>) -> Soft<T> {
+        Soft(minimum_number(self.0, other.0, &env()).0)
+    }
+
+    fn abs_sub(self, other: Soft<T>) -> Soft<T> {
+        if self <= other {
+            Soft::zero()
+        } else {
+            self - other
+        }
+    }
+
+    fn cbrt(self) -> Soft<T> {
+        self.via_f64(f64::cbrt)
+    }
+
+    fn hypot(self, other: Soft<T>) -> Soft<T> {
+        self.via_f64_2(other, f64::hypot)
+    }
+
+    fn sin(self) -> Soft<T> {
+        Soft(cr_sin(self.0, &env()).0)
+    }
+
+    fn cos(self) -> Soft<T> {
+        Soft(cr_cos(self.0, &env()).0)
+    }
+
+    fn tan(self) -> Soft<T> {
+        Soft(cr_tan(self.0, &env()).0)
+    }
+
+    fn asin(self) -> Soft<T> {
+        self.via_f64(f64::asin)
+    }
+
+    fn acos(self) -> Soft<T> {
+        self.via_f64(f64::acos)
+    }
+
+    fn atan(self) -> Soft<T> {
+        Soft(cr_atan(self.0, &env()).0)
+    }
+
+    fn atan2(self, other: Soft<T>) -> Soft<T> {
+        self.via_f64_2(other, f64::atan2)
+    }
+
+    fn sin_cos(self) -> (Soft<T>, Soft<T>) {
+        (self.sin(), self.cos())
+    }
+
+    fn exp_m1(self) -> Soft<T> {
+        self.via_f64(f64::exp_m1)
+    }
+
+    fn ln_1p(self) -> Soft<T> {
+        self.via_f64(f64::ln_1p)
+    }
+
+    fn sinh(self) -> Soft<T> {
+        self.via_f64(f64::sinh)
+    }
+
+    fn cosh(self) -> Soft<T> {
+        self.via_f64(f64::cosh)
+    }
+
+    fn tanh(self) -> Soft<T> {
+        self.via_f64(f64::tanh)
+    }
+
+    fn asinh(self) -> Soft<T> {
+        self.via_f64(f64::asinh)
+    }
+
+    fn acosh(self) -> Soft<T> {
+        self.via_f64(f64::acosh)
+    }
+
+    fn atanh(self) -> Soft<T> {
+        self.via_f64(f64::atanh)
+    }
+
+    // significands wider than 64 bits keep their top 64 bits
+    fn integer_decode(self) -> (u64, i16, i8) {
+        let (sign, mut exp, mut sig) = decode::<T>(&self.bits());
+        // like std, zeros and subnormals are shifted up a bit
+        if sig.bits() < T::SIG as u64 {
+            sig <<= 1;
+            exp -= 1;
+        }
+        let drop = (sig.bits() as i64 - 64).max(0);
+        let sig: u64 = (sig >> drop as usize).try_into().unwrap();
+        let exp = (exp + drop).clamp(i16::MIN as i64, i16::MAX as i64) as i16;
+        (sig, exp, if sign { -1 } else { 1 })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CustomFloat, ParseSoftError, Soft, Total};
+    use half::{bf16, f16};
+    use num_traits::{Float, NumCast, ToPrimitive};
+    use std::collections::HashSet;
+
+    #[test]
+    fn test() {
+        let mut state = 0x2545f4914f6cdd1du64;
+        for _ in 0..5000 {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let a = f32::from_bits((state >> 32) as u32);
+            let b = f32::from_bits(state as u32);
+            let (x, y) = (Soft(a), Soft(b));
+            for (res, expected) in [
+                (x + y, a + b),
+                (x - y, a - b),
+                (x * y, a * b),
+                (x / y, a / b),
+                (x % y, a % b),
+                (-x, -a),
+                (x.sqrt(), a.sqrt()),
+                (x.floor(), a.floor()),
+                (x.round(), a.round()),
+                (x.fract(), a.fract()),
+                (x.mul_add(y, y), a.mul_add(b, b)),
+                (x.max(y), a.max(b)),
+                (x.signum(), a.signum()),
+            ] {
+                if expected.is_nan() {
+                    assert!(res.is_nan());
+                } else {
+                    assert_eq!(res.0.to_bits(), expected.to_bits(), "{} {}", a, b);
+                }
+            }
+            assert_eq!(x == y, a == b);
+            assert_eq!(x.partial_cmp(&y), a.partial_cmp(&b));
+            assert_eq!(Total(a).cmp(&Total(b)), a.total_cmp(&b));
+            assert_eq!(x.to_i32(), ToPrimitive::to_i32(&a));
+            assert_eq!(x.to_u64(), ToPrimitive::to_u64(&a));
+            assert_eq!(x.integer_decode(), Float::integer_decode(a));
+        }
+    }
+
+    #[test]
+    fn test_traits() {
+        let a: Soft<f32> = "0.1".parse().unwrap();
+        let b: Soft<f32> = "0x1.8p1".parse().unwrap();
+        assert_eq!((a.0, b.0), (0.1, 3.0));
+        assert_eq!("1e".parse::<Soft<f32>>(), Err(ParseSoftError));
+        assert!("nan".parse::<Soft<f64>>().unwrap().is_nan());
+        assert_eq!(Soft(0.1f32).to_string(), "0.1");
+        assert_eq!(
+            format!("{:x} {:#010x}", Soft(1.0f32), Soft(-2.0f32)),
+            "3f800000 0xc0000000"
+        );
+        assert_eq!(format!("{:b}", Soft(f16::ONE)), "11110000000000");
+
+        // IEEE equality
+        let nan = Soft::<f32>::nan();
+        assert!(nan != nan);
+        assert_eq!(Soft(0.0f32), Soft(-0.0f32));
+        assert!(Soft(-0.0f32) <= Soft(0.0));
+        // total order
+        assert!(Total(-0.0f32) < Total(0.0));
+        assert_eq!(Total(f32::NAN), Total(f32::NAN));
+        let set: HashSet<Total<f32>> = [0.0, -0.0, f32::NAN, f32::NAN, 1.0].map(Total).into();
+        assert_eq!(set.len(), 4);
+        let mut sorted = [1.0, f32::NAN, -0.0, f32::NEG_INFINITY, 0.0].map(Total);
+        sorted.sort();
+        assert_eq!(
+            sorted.map(|a| a.0.to_bits()),
+            [f32::NEG_INFINITY, -0.0, 0.0, 1.0, f32::NAN].map(f32::to_bits)
+        );
+
+        // num_traits constants match the native ones
+        assert_eq!(Soft::<f64>::epsilon().0, f64::EPSILON);
+        assert_eq!(Soft::<f64>::min_positive_value().0, f64::MIN_POSITIVE);
+        assert_eq!(Soft::<f64>::min_value().0, f64::MIN);
+        assert_eq!(Soft::<bf16>::max_value().0, bf16::MAX);
+        assert_eq!(Soft::<f64>::neg_infinity().0, f64::NEG_INFINITY);
+        assert_eq!(Soft::<f64>::neg_zero().0.to_bits(), (-0.0f64).to_bits());
+
+        // casts
+        let big: Soft<f32> = NumCast::from(u64::MAX - 1).unwrap();
+        assert_eq!(big.0, u64::MAX as f32);
+        let int: Soft<f64> = NumCast::from((1i64 << 60) + 1).unwrap();
+        assert_eq!(int.0, ((1i64 << 60) + 1) as f64);
+        let half: Soft<f16> = NumCast::from(0.5f64).unwrap();
+        assert_eq!(half.0, f16::from_f32(0.5));
+        assert_eq!(Soft(-1.5f32).to_u8(), None);
+        assert_eq!(Soft(300.7f32).to_u8(), None);
+        assert_eq!(Soft(-1.5f32).to_i8(), Some(-1));
+        assert_eq!(Soft(f32::NAN).to_i64(), None);
+    }
+
+    // generic code written against num_traits runs on any format
+    fn norm<F: Float>(x: &[F]) -> F {
+        x.iter().fold(F::zero(), |sum, &a| a.mul_add(a, sum)).sqrt()
+    }
+
+    #[test]
+    fn test_generic() {
+        let x = [3.0f32, 4.0, 12.0];
+        assert_eq!(norm(&x.map(Soft)).0, 13.0);
+        assert_eq!(norm(&x.map(f16::from_f32).map(Soft)).0, f16::from_f32(13.0));
+        type F128 = CustomFloat<15, 113>;
+        let x: [Soft<F128>; 3] = x.map(|a| NumCast::from(a).unwrap());
+        assert_eq!(norm(&x).to_f64(), Some(13.0));
+
+        // correctly rounded where std may not be
+        let one = Soft(1.0f64);
+        assert_eq!(one.exp().0, std::f64::consts::E);
+        assert_eq!(one.atan().0 * 4.0, std::f64::consts::PI);
+        assert_eq!(Soft(0.5f64).exp2().0, std::f64::consts::SQRT_2);
+        assert_eq!(Soft(8.0f32).log2().0, 3.0);
+        assert_eq!(Soft(2.0f32).powi(-2).0, 0.25);
+        // integer exponents beyond 2^SIG are not rounded to T
+        assert_eq!(Soft(bf16::from_f32(-1.0)).powi(257).0, bf16::from_f32(-1.0));
+        assert_eq!(Soft(f16::from_f32(-1.0)).powi(65537).0, f16::from_f32(-1.0));
+        let x = f16::from_f64(1.0 + 2f64.powi(-10));
+        let expected = f16::from_f64(x.to_f64().powi(2049));
+        assert_eq!(Soft(x).powi(2049).0, expected);
+        assert_ne!(Soft(x).powi(2048).0, expected);
+        // fused multiply-add keeps the product exact
+        let a = Soft(1.0 + f64::EPSILON);
+        let b = Soft(1.0 - f64::EPSILON);
+        assert_eq!(a.mul_add(b, -one).0, -f64::EPSILON * f64::EPSILON);
+        assert_eq!((a * b - one).0, 0.0);
+        // and quiets a signaling nan addend like the native one
+        let snan = Soft(f32::from_bits(0x7f800001));
+        let res = Soft(1.0f32).mul_add(Soft(1.0), snan).0;
+        assert_eq!(res.to_bits(), 0x7fc00001);
+        assert_eq!(res.to_bits(), 1.0f32.mul_add(1.0, snan.0).to_bits());
+        let res = Soft(f32::NAN).mul_add(Soft(0.0), snan).0;
+        assert_eq!(res.to_bits(), f32::NAN.to_bits());
+        assert_eq!(
+            Soft(1.0f32).mul_add(Soft(2.0), Soft(f32::NEG_INFINITY)).0,
+            f32::NEG_INFINITY
+        );
+    }
+}